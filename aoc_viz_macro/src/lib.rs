@@ -0,0 +1,162 @@
+//! Procedural macros of aoc_viz.
+//!
+//! Use them through the `aoc_viz` crate, which re-exports them.
+
+extern crate proc_macro;
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{Error, ItemFn, Lit, Meta, MetaNameValue, Token};
+
+/// Turns a visualization function into a visualizer binary.
+///
+/// The tagged function must take the puzzle input as its only argument,
+/// and return an `Iterator` over `Visualize` implementors, e.g.
+/// `fn(String) -> impl Iterator<Item = V>`.
+/// A `main` function is generated, that reads the input and launches an
+/// `AocVizApp` running the tagged function.
+///
+/// Options:
+/// * `input = "path"`: file read as the input when none is given on the
+///   command-line.
+///
+/// ```ignore
+/// #[aoc_viz(input = "input/2018/day1.txt")]
+/// fn compute(input: String) -> impl Iterator<Item = Day1Viz> {
+///     // ...
+/// }
+/// ```
+#[proc_macro_attribute]
+pub fn aoc_viz(
+    attr: proc_macro::TokenStream,
+    item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    expand(attr.into(), item.into())
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Options given to the `aoc_viz` attribute
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Options {
+    /// Default input file
+    input: Option<String>,
+}
+
+/// Parses the arguments of the `aoc_viz` attribute
+fn parse_options(attr: TokenStream) -> syn::Result<Options> {
+    let mut options = Options::default();
+    let args = Punctuated::<Meta, Token![,]>::parse_terminated.parse2(attr)?;
+    for arg in args {
+        match arg {
+            Meta::NameValue(MetaNameValue {
+                ref path,
+                lit: Lit::Str(ref value),
+                ..
+            }) if path.is_ident("input") => options.input = Some(value.value()),
+            arg => {
+                return Err(Error::new(
+                    arg.span(),
+                    "Unknown option, expected `input = \"path\"`",
+                ))
+            }
+        }
+    }
+    Ok(options)
+}
+
+/// Generates the tagged function, followed by the `main` function launching it
+fn expand(attr: TokenStream, item: TokenStream) -> syn::Result<TokenStream> {
+    let options = parse_options(attr)?;
+    let item_fn: ItemFn = syn::parse2(item)?;
+    let sig = &item_fn.sig;
+
+    if sig.inputs.len() != 1 {
+        return Err(Error::new(
+            sig.inputs.span(),
+            "#[aoc_viz] functions take the puzzle input as their only argument",
+        ));
+    }
+    if sig.asyncness.is_some() || !sig.generics.params.is_empty() {
+        return Err(Error::new(
+            sig.span(),
+            "#[aoc_viz] functions can not be async nor generic",
+        ));
+    }
+
+    let name = &sig.ident;
+    let default_input = options
+        .input
+        .map(|path| quote!(.with_default_input_file(#path)));
+
+    Ok(quote! {
+        #item_fn
+
+        fn main() {
+            use ::aoc_viz::aoc_viz_app::{AocVizApp, LaunchError};
+            let app = AocVizApp::from_env(#name).unwrap_or_else(|e| LaunchError::from(e).exit());
+            let mut app = app #default_input;
+            if let Err(e) = app.launch() {
+                e.exit();
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn aoc_viz_options() {
+        assert_eq!(parse_options(quote!()).unwrap(), Options::default());
+        assert_eq!(
+            parse_options(quote!(input = "day1.txt")).unwrap().input,
+            Some("day1.txt".to_string())
+        );
+        assert!(parse_options(quote!(output = "day1.txt")).is_err());
+        assert!(parse_options(quote!(input = 1)).is_err());
+    }
+
+    #[test]
+    pub fn aoc_viz_expand() {
+        let item = quote! {
+            fn compute(input: String) -> impl Iterator<Item = u32> {
+                input.lines().filter_map(|l| l.parse().ok()).collect::<Vec<_>>().into_iter()
+            }
+        };
+        let expanded = expand(quote!(input = "day1.txt"), item)
+            .unwrap()
+            .to_string();
+
+        assert!(expanded.contains("fn compute"));
+        assert!(expanded.contains("fn main"));
+        assert!(expanded.contains("AocVizApp :: from_env (compute)"));
+        assert!(expanded.contains("with_default_input_file (\"day1.txt\")"));
+    }
+
+    #[test]
+    pub fn aoc_viz_expand_errors() {
+        let no_input = quote!(
+            fn compute() -> Vec<u32> {
+                vec![]
+            }
+        );
+        assert!(expand(quote!(), no_input).is_err());
+
+        let generic = quote!(
+            fn compute<T>(input: T) -> Vec<T> {
+                vec![input]
+            }
+        );
+        assert!(expand(quote!(), generic).is_err());
+
+        let not_a_fn = quote!(
+            struct NotAFunction;
+        );
+        assert!(expand(quote!(), not_a_fn).is_err());
+    }
+}
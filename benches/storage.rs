@@ -0,0 +1,131 @@
+//! Compares the storages of a DiffCache on a dense puzzle, where every
+//! position of a 200x200 grid changes on every frame
+use aoc_viz::coords::Bounds;
+use aoc_viz::diff_cache::DiffCache;
+use aoc_viz::storage::AnyStorage;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const SIZE: i32 = 200;
+const FRAMES: usize = 10;
+
+type Cache = DiffCache<(i32, i32), usize, u8>;
+type Storage = AnyStorage<(i32, i32), usize, u8>;
+
+/// Names of the compared storages
+const STORAGES: [&str; 2] = ["sparse", "dense"];
+
+/// Counts the bytes allocated on the heap, to compare the memory used by
+/// the storages
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Creates the storage of the given name, covering the grid if it is dense
+fn storage(name: &str) -> Storage {
+    match name {
+        "dense" => {
+            let mut bounds = Bounds::new(0, 0);
+            bounds.include(i64::from(SIZE) - 1, i64::from(SIZE) - 1);
+            AnyStorage::dense(bounds)
+        }
+        _ => AnyStorage::sparse(),
+    }
+}
+
+fn grid() -> impl Iterator<Item = (i32, i32)> {
+    (0..SIZE).flat_map(|y| (0..SIZE).map(move |x| (x, y)))
+}
+
+fn populate(storage: Storage) -> Cache {
+    let mut cache = DiffCache::with_storage(0, storage);
+    for index in 0..FRAMES {
+        grid().for_each(|c| {
+            cache
+                .push(c, index, (c.0 + c.1) as u8 ^ index as u8)
+                .unwrap()
+        });
+    }
+    cache
+}
+
+fn bench_populate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("populate");
+    group.sample_size(10);
+    for name in STORAGES.iter() {
+        group.bench_function(BenchmarkId::from_parameter(*name), |b| {
+            b.iter(|| populate(storage(name)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_frame(c: &mut Criterion) {
+    let mut group = c.benchmark_group("frame");
+    for name in STORAGES.iter() {
+        let cache = populate(storage(name));
+        group.bench_function(BenchmarkId::from_parameter(*name), |b| {
+            b.iter(|| {
+                let frame = cache.frame(black_box(FRAMES / 2));
+                grid().map(|c| u64::from(*frame.get(&c))).sum::<u64>()
+            })
+        });
+    }
+    group.finish();
+}
+
+fn bench_search(c: &mut Criterion) {
+    let mut group = c.benchmark_group("search");
+    for name in STORAGES.iter() {
+        let cache = populate(storage(name));
+        group.bench_function(BenchmarkId::from_parameter(*name), |b| {
+            b.iter(|| {
+                grid()
+                    .map(|c| u64::from(*cache.search_or_default(c, black_box(FRAMES / 2))))
+                    .sum::<u64>()
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Prints the bytes allocated by each storage once populated, next to the
+/// estimate of the DiffCache (cf. `DiffCache::memory_usage`)
+fn bench_memory(_: &mut Criterion) {
+    for name in STORAGES.iter() {
+        let before = ALLOCATED.load(Ordering::Relaxed);
+        let cache = populate(storage(name));
+        let allocated = ALLOCATED.load(Ordering::Relaxed) - before;
+        println!(
+            "memory/{:<8} allocated: {:>10} bytes, estimated: {:>10} bytes",
+            name,
+            allocated,
+            cache.memory_usage()
+        );
+    }
+}
+
+criterion_group!(
+    benches,
+    bench_populate,
+    bench_frame,
+    bench_search,
+    bench_memory
+);
+criterion_main!(benches);
@@ -1,47 +1,33 @@
-use aoc_viz::aoc_viz_app::AocVizApp;
+use aoc_viz::aoc_viz;
+use std::fmt;
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct Day1Viz {
-    curr_val: i32, 
+    curr_val: i32,
     sum: i32,
 }
 
-impl ToString for Day1Viz {
-    fn to_string(&self) -> String {
-        if self.curr_val == 0 && self.sum == 0 { return format!(""); }
-        format!("current: {}\nsum: {}", self.curr_val, self.sum)
-    }
-}
-
-impl Default for Day1Viz {
-    fn default() -> Self {
-        Day1Viz {
-            curr_val: 0,
-            sum: 0,
+impl fmt::Display for Day1Viz {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.curr_val == 0 && self.sum == 0 {
+            return Ok(());
         }
+        write!(f, "current: {}\nsum: {}", self.curr_val, self.sum)
     }
 }
 
-fn compute(_input: String) -> impl Iterator<Item= Day1Viz> {
-    let input = include_str!("input/2018_day1.txt");
-
+/// Run with `cargo run --example 2018_day_1`, which reads `examples/input/2018_day1.txt`,
+/// or pass your own puzzle input instead
+#[aoc_viz(input = "examples/input/2018_day1.txt")]
+fn compute(input: String) -> impl Iterator<Item = Day1Viz> {
     let mut sum = 0;
     input
-    .lines()
-    .filter_map(|a| a.parse::<i32>().ok())
-    .map(move |x| {
-        sum += x;
-        Day1Viz {
-            curr_val: x,
-            sum: sum,
-        }
-    })
-
+        .lines()
+        .filter_map(|a| a.parse::<i32>().ok())
+        .map(move |x| {
+            sum += x;
+            Day1Viz { curr_val: x, sum }
+        })
+        .collect::<Vec<_>>()
+        .into_iter()
 }
-
-fn main() {
-
-    let mut app = AocVizApp::new(compute);
-    app.launch();
-
-}
\ No newline at end of file
@@ -0,0 +1,102 @@
+use aoc_viz::aoc_viz_app::{AocVizApp, LaunchError};
+use aoc_viz::cell::Cell;
+use aoc_viz::visualize::Visualize;
+use cursive::theme::{BaseColor, Color, Effect};
+use std::collections::HashSet;
+
+const MAZE: &str = "\
+###########
+#.....#...#
+#.###.#.#.#
+#...#...#.#
+###.#####.#
+#.........#
+###########";
+
+/// A walker going through the maze, remembering where it's been
+#[derive(Debug, Default, Clone)]
+pub struct Walk {
+    walls: HashSet<(i32, i32)>,
+    visited: HashSet<(i32, i32)>,
+    position: (i32, i32),
+}
+
+impl Visualize<(i32, i32), Cell> for Walk {
+    fn default_val(&self) -> Cell {
+        Cell::new(' ')
+    }
+
+    fn get(&self, coords: &(i32, i32)) -> Option<Cell> {
+        if *coords == self.position {
+            Some(
+                Cell::new('@')
+                    .fg(Color::Light(BaseColor::Yellow))
+                    .effect(Effect::Bold),
+            )
+        } else if self.walls.contains(coords) {
+            Some(Cell::new('#').bg(Color::Dark(BaseColor::Blue)))
+        } else if self.visited.contains(coords) {
+            Some(Cell::new('.').fg(Color::Light(BaseColor::Green)))
+        } else {
+            None
+        }
+    }
+
+    fn delta(&self, previous: &Self) -> Vec<(i32, i32)> {
+        self.walls
+            .symmetric_difference(&previous.walls)
+            .chain(self.visited.symmetric_difference(&previous.visited))
+            .chain([self.position, previous.position].iter())
+            .cloned()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect()
+    }
+}
+
+/// Walks the maze by following the right-hand wall
+fn walk(_input: String) -> impl Iterator<Item = Walk> {
+    let walls: HashSet<(i32, i32)> = MAZE
+        .lines()
+        .enumerate()
+        .flat_map(|(y, line)| {
+            line.chars()
+                .enumerate()
+                .filter(|(_, c)| *c == '#')
+                .map(move |(x, _)| (x as i32, y as i32))
+        })
+        .collect();
+
+    let directions = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+    let mut walk = Walk {
+        walls,
+        visited: HashSet::new(),
+        position: (1, 1),
+    };
+    let mut facing = 0;
+
+    (0..60).map(move |_| {
+        walk.visited.insert(walk.position);
+        // Tries right, straight, left, then back
+        facing = (facing + 1) % 4;
+        for _ in 0..4 {
+            let (dx, dy) = directions[facing];
+            let next = (walk.position.0 + dx, walk.position.1 + dy);
+            if !walk.walls.contains(&next) {
+                walk.position = next;
+                break;
+            }
+            facing = (facing + 3) % 4;
+        }
+        walk.clone()
+    })
+}
+
+fn main() {
+    let launched = AocVizApp::from_env(walk)
+        .map_err(LaunchError::from)
+        .and_then(|mut app| app.launch());
+    if let Err(e) = launched {
+        e.exit();
+    }
+}
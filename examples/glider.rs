@@ -0,0 +1,135 @@
+use aoc_viz::aoc_viz_app::{AocVizApp, LaunchError};
+use aoc_viz::breakpoint::Breakpoint;
+use aoc_viz::cell::{Cell, Displayable};
+use aoc_viz::coords::{Bounds, Coords};
+use aoc_viz::view::aggregation::Aggregation;
+use aoc_viz::visualize::Visualize;
+use cursive::theme::{BaseColor, Color};
+use std::collections::{HashMap, HashSet};
+
+/// A point on an infinite grid
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Point {
+    x: i64,
+    y: i64,
+}
+
+impl Coords for Point {
+    fn from_xy(x: i64, y: i64) -> Option<Self> {
+        Some(Point { x, y })
+    }
+
+    fn x(&self) -> i64 {
+        self.x
+    }
+
+    fn y(&self) -> i64 {
+        self.y
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Tile {
+    Dead,
+    Alive,
+}
+
+impl Displayable for Tile {
+    fn to_cell(&self) -> Cell {
+        match self {
+            Tile::Dead => Cell::new(' '),
+            Tile::Alive => Cell::new('O').fg(Color::Light(BaseColor::Cyan)),
+        }
+    }
+}
+
+/// A Game of Life generation
+#[derive(Debug, Default)]
+pub struct Life {
+    grid: HashMap<Point, Tile>,
+}
+
+impl Visualize<Point, Tile> for Life {
+    fn default_val(&self) -> Tile {
+        Tile::Dead
+    }
+
+    fn get(&self, coords: &Point) -> Option<Tile> {
+        self.grid.get(coords).cloned()
+    }
+
+    fn delta(&self, previous: &Self) -> Vec<Point> {
+        self.grid
+            .keys()
+            .chain(previous.grid.keys())
+            .filter(|p| self.grid.get(p) != previous.grid.get(p))
+            .cloned()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .collect()
+    }
+}
+
+impl Life {
+    /// Computes the next generation of the grid
+    fn step(&self) -> Life {
+        let mut neighbours: HashMap<Point, usize> = HashMap::new();
+        self.grid
+            .iter()
+            .filter(|(_, t)| **t == Tile::Alive)
+            .for_each(|(p, _)| {
+                (-1..=1)
+                    .flat_map(|dy| (-1..=1).map(move |dx| (dx, dy)))
+                    .filter(|d| *d != (0, 0))
+                    .for_each(|(dx, dy)| {
+                        let n = Point {
+                            x: p.x + dx,
+                            y: p.y + dy,
+                        };
+                        *neighbours.entry(n).or_insert(0) += 1;
+                    })
+            });
+
+        let grid = neighbours
+            .into_iter()
+            .filter(|(p, n)| *n == 3 || (*n == 2 && self.grid.get(p) == Some(&Tile::Alive)))
+            .map(|(p, _)| (p, Tile::Alive))
+            .collect();
+        Life { grid }
+    }
+}
+
+fn glider(_input: String) -> impl Iterator<Item = Life> {
+    let grid = vec![(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)]
+        .into_iter()
+        .map(|(x, y)| (Point { x, y }, Tile::Alive))
+        .collect();
+    let first = Life { grid };
+    std::iter::successors(Some(first), |life| Some(life.step())).take(100)
+}
+
+fn main() {
+    // Stops once the glider leaves the area it started in
+    let mut start = Bounds::new(0, 0);
+    start.include(15, 15);
+    // Packs two rows of cells per character
+    let app = AocVizApp::from_env(glider)
+        .unwrap_or_else(|e| LaunchError::from(e).exit())
+        .with_aggregation(Aggregation::HalfBlock)
+        .with_lit_predicate(|tile| *tile == Tile::Alive)
+        .with_breakpoint(Breakpoint::value_outside(Tile::Alive, start))
+        .with_breakpoint(Breakpoint::on_item("population changed", |life: &Life| {
+            life.grid.len() != 5
+        }))
+        // Compares the generations on two threads
+        .with_workers(2);
+    // Accepts the --save and --load arguments
+    #[cfg(feature = "serde")]
+    let app = app.with_snapshots();
+    let mut app = app;
+    if let Err(e) = app.launch() {
+        e.exit();
+    }
+}
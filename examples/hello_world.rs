@@ -1,4 +1,4 @@
-use aoc_viz::aoc_viz_app::AocVizApp;
+use aoc_viz::aoc_viz_app::{AocVizApp, LaunchError};
 
 fn test_aoc_viz(_input: String) -> impl Iterator<Item = &'static str> {
     vec![
@@ -13,6 +13,10 @@ fn test_aoc_viz(_input: String) -> impl Iterator<Item = &'static str> {
 }
 
 fn main() {
-    let mut app = AocVizApp::new(test_aoc_viz);
-    app.launch();
+    let launched = AocVizApp::from_env(test_aoc_viz)
+        .map_err(LaunchError::from)
+        .and_then(|mut app| app.launch());
+    if let Err(e) = launched {
+        e.exit();
+    }
 }
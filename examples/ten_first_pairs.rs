@@ -1,10 +1,14 @@
-use aoc_viz::aoc_viz_app::AocVizApp;
+use aoc_viz::aoc_viz_app::{AocVizApp, LaunchError};
 
 fn test_aoc_viz(_input: String) -> impl Iterator<Item = u32> {
     (1..10).filter(|x| x % 2 == 0)
 }
 
 fn main() {
-    let mut app = AocVizApp::new(test_aoc_viz);
-    app.launch();
+    let launched = AocVizApp::from_env(test_aoc_viz)
+        .map_err(LaunchError::from)
+        .and_then(|mut app| app.launch());
+    if let Err(e) = launched {
+        e.exit();
+    }
 }
@@ -1,60 +1,274 @@
+use crate::args::{Args, ArgsError, USAGE};
+use crate::breakpoint::Breakpoint;
+use crate::cell::Displayable;
+use crate::coords::Coords;
 use crate::diff_cache::DiffCache;
-use crate::time_index::TimeIndex;
-use crate::view::frame::FrameView;
+use crate::export::asciicast::{export_asciicast, CastOptions};
+use crate::export::gif::{export_gif, GifOptions};
+use crate::input::InputSource;
+#[cfg(feature = "serde")]
+use crate::recording::{RecordingReader, RecordingWriter};
+use crate::shared_cache::SharedDiffCache;
+#[cfg(feature = "serde")]
+use crate::snapshot;
+use crate::storage::AnyStorage;
+use crate::time_index::{Generation, TimeIndex};
+use crate::view::aggregation::Aggregation;
+use crate::view::frame::{FrameView, LitPredicate};
 use crate::view::time_view::TimeView;
-use crate::visualize::{populate_cache, Visualize};
+use crate::visualize::{populate_cache_parallel, populate_cache_with, Visualize};
+use crossbeam_channel::Sender;
 use cursive::direction::Orientation;
 use cursive::view::{Boxable, Identifiable, Selector};
 use cursive::views::{Dialog, EditView, LinearLayout};
-use cursive::Cursive;
+use cursive::{CbFunc, Cursive};
+#[cfg(feature = "serde")]
+use serde::{de::DeserializeOwned, Serialize};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter};
 use std::marker::PhantomData;
-use std::sync::{Arc, Mutex};
+#[cfg(feature = "serde")]
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
 
-pub struct AocVizApp<F, T, V> {
-    cursive: Cursive,
-    cache: Arc<Mutex<DiffCache<(i32, i32), usize, char>>>,
+/// Visualizes the states yielded by the user's fn, of type V,
+/// showing values of type D on a plane of C coordinates
+pub struct AocVizApp<F, T, V, C: Coords, D> {
+    cache: SharedDiffCache<C, D>,
     time_index: Arc<Mutex<TimeIndex>>,
     fn_user: F,
+    /// Where the input of the user's fn comes from. An empty String if unspecified
+    input: Option<InputSource>,
+    /// If set, the visualization is exported to this GIF instead of being shown
+    gif: Option<PathBuf>,
+    /// If set, the visualization is exported to this asciicast instead of being shown
+    cast: Option<PathBuf>,
+    /// If set, a snapshot of the frames is saved to this file instead of being shown
+    save: Option<PathBuf>,
+    /// If set, the frames are read from this snapshot instead of running the user's fn
+    load: Option<PathBuf>,
+    /// If set, each frame is written to this recording as soon as it is produced
+    record: Option<PathBuf>,
+    /// If set, the frames are read from this recording instead of running the user's fn
+    replay: Option<PathBuf>,
+    /// Saves and loads the snapshots, if enabled
+    #[cfg(feature = "serde")]
+    snapshots: Option<SnapshotIo<C, D>>,
+    /// Conditions stopping the playback and the follow mode
+    breakpoints: Vec<Breakpoint<C, V, D>>,
+    /// How the FrameView summarizes blocks of coordinates, initially
+    aggregation: Aggregation,
+    /// Tells the FrameView which values are lit, if set
+    is_lit: Option<LitPredicate<D>>,
+    /// Computes the changes between the states on worker threads, if set
+    workers: Option<Workers<V, C, D>>,
+    /// Storage of the frames, if not the default one
+    storage: Option<AnyStorage<C, usize, D>>,
+    /// Memory the frames may use, if limited
+    memory_budget: Option<MemoryBudget<C, D>>,
     _phantom_t: PhantomData<T>,
     _phantom_v: PhantomData<V>,
 }
 
-impl<F, T, V> AocVizApp<F, T, V>
+impl<F, T, V, C, D> AocVizApp<F, T, V, C, D>
 where
     F: Fn(String) -> T + Clone + Send + Sync + 'static,
     T: Iterator<Item = V>,
-    V: Visualize<(i32, i32), char> + std::fmt::Debug + std::default::Default,
+    V: Visualize<C, D> + std::fmt::Debug + std::default::Default + 'static,
+    C: Coords,
+    D: Displayable + std::fmt::Debug,
 {
-    /// Creates an instance of an AocVizApp
+    /// Creates an instance of an AocVizApp, configured only through its
+    /// builder methods. The command-line arguments are ignored
+    /// (cf. `AocVizApp::from_env`)
     pub fn new(fn_user: F) -> Self {
-        // Creates the cursive
-        let mut cursive = Cursive::default();
-        cursive
-            .load_theme_file("assets/default_theme.toml")
-            .expect("Failed to load theme");
+        Self::from_args(fn_user, Args::default())
+    }
+
+    /// Creates an instance of an AocVizApp, taking the input given to the
+    /// user's fn and the export options from the command-line arguments of
+    /// the running binary (cf. `Args::parse`)
+    pub fn from_env(fn_user: F) -> Result<Self, ArgsError> {
+        Ok(Self::from_args(fn_user, Args::from_env()?))
+    }
 
+    /// Creates an instance of an AocVizApp, taking the input given to the
+    /// user's fn and the export options from the given arguments
+    pub fn from_args(fn_user: F, args: Args) -> Self {
         AocVizApp {
-            cursive,
-            cache: Arc::new(Mutex::new(DiffCache::new(' '))),
+            cache: SharedDiffCache::new(DiffCache::new(V::default().default_val())),
             time_index: Arc::new(Mutex::new(TimeIndex::new(0, 0, 0))),
             fn_user,
+            input: args.input,
+            gif: args.gif,
+            cast: args.cast,
+            save: args.save,
+            load: args.load,
+            record: args.record,
+            replay: args.replay,
+            #[cfg(feature = "serde")]
+            snapshots: None,
+            breakpoints: Vec::new(),
+            aggregation: Aggregation::default(),
+            is_lit: None,
+            workers: None,
+            storage: None,
+            memory_budget: None,
             _phantom_t: PhantomData,
             _phantom_v: PhantomData,
         }
     }
 
+    /// Uses the given String as the input of the user's fn
+    pub fn with_input(self, input: String) -> Self {
+        self.with_input_source(InputSource::Raw(input))
+    }
+
+    /// Reads the input of the user's fn from the file at the given path
+    pub fn with_input_file(self, path: impl Into<PathBuf>) -> Self {
+        self.with_input_source(InputSource::File(path.into()))
+    }
+
+    /// Reads the input of the user's fn from stdin
+    pub fn with_stdin(self) -> Self {
+        self.with_input_source(InputSource::Stdin)
+    }
+
+    /// Specifies where the input of the user's fn comes from
+    pub fn with_input_source(mut self, input: InputSource) -> Self {
+        self.input = Some(input);
+        self
+    }
+
+    /// Reads the input of the user's fn from the file at the given path,
+    /// unless another input was specified (e.g. on the command-line)
+    pub fn with_default_input_file(mut self, path: impl Into<PathBuf>) -> Self {
+        if self.input.is_none() {
+            self.input = Some(InputSource::File(path.into()));
+        }
+        self
+    }
+
+    /// Exports the visualization to the GIF at the given path instead of
+    /// showing it, when launched
+    pub fn with_gif_export(mut self, path: impl Into<PathBuf>) -> Self {
+        self.gif = Some(path.into());
+        self
+    }
+
+    /// Exports the visualization to the asciicast v2 recording at the given
+    /// path instead of showing it, when launched
+    pub fn with_asciicast_export(mut self, path: impl Into<PathBuf>) -> Self {
+        self.cast = Some(path.into());
+        self
+    }
+
+    /// Keeps the frames in the given storage, e.g. `AnyStorage::dense(bounds)`
+    /// when most of a known rectangle changes. By default, a dense grid is
+    /// picked once most of the bounds of the content got a value
+    pub fn with_storage(mut self, storage: AnyStorage<C, usize, D>) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    /// Keeps the frames under about the given number of bytes, decimating the
+    /// oldest ones once over budget (cf. `DiffCache::with_memory_budget`),
+    /// whether they come from the user's fn, a snapshot or a recording.
+    /// The budget covers both copies of the frames (cf. `SharedDiffCache`),
+    /// each one getting half of it
+    pub fn with_memory_budget(mut self, bytes: usize) -> Self
+    where
+        D: PartialEq,
+    {
+        self.memory_budget = Some(MemoryBudget {
+            bytes,
+            apply: DiffCache::with_memory_budget,
+        });
+        self
+    }
+
+    /// Computes the changes between consecutive states of the user's fn on
+    /// the given number of worker threads, while the user's fn keeps running
+    /// on its own thread (cf. `populate_cache_parallel`)
+    pub fn with_workers(mut self, count: usize) -> Self
+    where
+        V: Send + Sync,
+        C: Sync,
+        D: Sync,
+    {
+        self.workers = Some(Workers {
+            count,
+            populate: populate_in_parallel,
+        });
+        self
+    }
+
+    /// Stops the playback and the follow mode at the frames hitting the Breakpoint.
+    /// The frames of a snapshot or a recording are searched for changes hitting
+    /// it, but the states of the user's fn are not stored: Breakpoints on them
+    /// (cf. `Breakpoint::on_item`) are only checked while it runs
+    pub fn with_breakpoint(mut self, breakpoint: Breakpoint<C, V, D>) -> Self {
+        self.breakpoints.push(breakpoint);
+        self
+    }
+
+    /// Summarizes blocks of coordinates with the given aggregation when
+    /// launched, e.g. `Aggregation::Braille` to draw 2x4 coordinates per character
+    pub fn with_aggregation(mut self, aggregation: Aggregation) -> Self {
+        self.aggregation = aggregation;
+        self
+    }
+
+    /// Draws the values matching the predicate as lit pixels, instead of
+    /// the ones shown differently from the default value
+    pub fn with_lit_predicate(mut self, is_lit: impl Fn(&D) -> bool + 'static) -> Self {
+        self.is_lit = Some(Box::new(is_lit));
+        self
+    }
+
     /// Launches the viz application
-    pub fn launch(&mut self) {
+    /// Fails before starting if the arguments ask for snapshots while they
+    /// are not enabled (cf. `check_args`), or if the input, the snapshot or
+    /// the recording could not be read. Fails if the export failed.
+    /// The frames are shared with the view across threads, hence `Sync`
+    pub fn launch(&mut self) -> Result<(), LaunchError>
+    where
+        C: Sync,
+        D: Sync,
+    {
+        self.check_args()?;
+
+        // Reads the input before cursive takes over the terminal
+        let frames = self.frames()?;
+
+        if self.gif.is_some() || self.cast.is_some() || self.save.is_some() {
+            return Ok(self.export(frames)?);
+        }
+
+        // Creates the cursive
+        let mut cursive = Cursive::default();
+        cursive
+            .load_toml(DEFAULT_THEME)
+            .expect("Failed to load theme");
+
         // Populates the view
         let mut layout = LinearLayout::new(Orientation::Vertical);
-        layout.add_child(FrameView::new(self.cache.clone(), self.time_index.clone()));
+        let mut frame_view = FrameView::new(self.cache.clone(), self.time_index.clone())
+            .with_aggregation(self.aggregation);
+        if let Some(is_lit) = self.is_lit.take() {
+            frame_view = frame_view.with_lit_predicate(is_lit);
+        }
+        layout.add_child(frame_view.with_id("frame_view"));
         layout.add_child(TimeView::new(self.time_index.clone()).with_id("time_view"));
 
-        self.cursive.add_layer(layout);
+        cursive.add_layer(layout);
 
         // Sets the various option callbacks
-        self.cursive.add_global_callback('q', |c| c.quit());
-        self.cursive.add_global_callback('g', |c| {
+        cursive.add_global_callback('q', |c| c.quit());
+        cursive.add_global_callback('g', |c| {
             c.add_layer(
                 Dialog::new()
                     .title("Go to time index")
@@ -70,8 +284,7 @@ where
                                         time_view.set_current_time_frame(response)
                                     },
                                 ) {
-                                    Some(rep) if rep == true => (),
-                                    Some(rep) if rep == false => (),
+                                    Some(_) => (),
                                     _ => unreachable!(),
                                 }
                             })
@@ -82,47 +295,953 @@ where
             )
         });
 
-        // Populates the cache by running the user's fn with a correct input
-        CachePopulator::new(
-            self.cache.clone(),
-            Box::new(self.fn_user.clone()),
-            self.time_index.clone(),
-        )
-        .launch();
+        match frames {
+            // Populates the cache by running the user's fn with a correct input
+            Frames::Run(input, recorder) => {
+                CachePopulator::new(
+                    self.cache.clone(),
+                    Box::new(self.fn_user.clone()),
+                    self.time_index.clone(),
+                    input,
+                    std::mem::take(&mut self.breakpoints),
+                    recorder,
+                    cursive.cb_sink().clone(),
+                )
+                .with_workers(self.workers.take())
+                .launch();
+
+                // Redraws regularly while the frames are being generated
+                cursive.set_fps(GENERATION_FPS);
+            }
+            // Keeps reading the frames appended to the recording
+            Frames::Replay(source) => RecordingTail::new(
+                self.cache.clone(),
+                self.time_index.clone(),
+                source,
+                std::mem::take(&mut self.breakpoints),
+                cursive.cb_sink().clone(),
+            )
+            .launch(),
+            Frames::Loaded => (),
+        }
+
+        // Moves the time frames forward when the playback is running
+        Player::new(self.time_index.clone(), cursive.cb_sink().clone()).launch();
 
         // Runs the cursive app
-        self.cursive.run();
+        cursive.run();
+        Ok(())
+    }
+
+    /// Checks that the options reading or writing snapshots are only given
+    /// when snapshots are enabled (cf. `with_snapshots`)
+    pub fn check_args(&self) -> Result<(), ArgsError> {
+        if self.snapshots_enabled() {
+            return Ok(());
+        }
+        let options = [
+            ("--save", &self.save),
+            ("--load", &self.load),
+            ("--record", &self.record),
+            ("--replay", &self.replay),
+        ];
+        match options.iter().find(|(_, path)| path.is_some()) {
+            Some((option, _)) => Err(ArgsError::SnapshotsDisabled(option.to_string())),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether the snapshots can be saved and loaded
+    #[cfg(feature = "serde")]
+    fn snapshots_enabled(&self) -> bool {
+        self.snapshots.is_some()
+    }
+
+    /// Snapshots can not be saved nor loaded without the serde feature
+    #[cfg(not(feature = "serde"))]
+    fn snapshots_enabled(&self) -> bool {
+        false
+    }
+
+    /// Tells where the frames come from: the user's fn run on its input,
+    /// unless a snapshot or a recording is opened instead
+    fn frames(&self) -> io::Result<Frames<C, D>> {
+        if self.load_snapshot()? {
+            return Ok(Frames::Loaded);
+        }
+        if let Some(source) = self.open_replay()? {
+            return Ok(Frames::Replay(source));
+        }
+        let input = self
+            .input
+            .clone()
+            .unwrap_or_else(|| InputSource::Raw(String::new()))
+            .read()?;
+        self.cache
+            .replace(self.new_cache(V::default().default_val()));
+        Ok(Frames::Run(input, self.create_recorder()?))
+    }
+
+    /// Creates the cache the frames are stored in, with the storage and the
+    /// memory budget given to the builder
+    fn new_cache(&self, default: D) -> DiffCache<C, usize, D> {
+        (self.cache_builder())(default)
+    }
+
+    /// Builds the caches the frames are stored in like `new_cache`, once
+    /// given their default value
+    fn cache_builder(&self) -> impl FnOnce(D) -> DiffCache<C, usize, D> + Send + 'static {
+        let storage = self.storage.clone().unwrap_or_default();
+        let budget = self.memory_budget;
+        move |default| {
+            let cache = DiffCache::with_storage(default, storage);
+            match budget {
+                Some(budget) => budget.apply_to(cache),
+                None => cache,
+            }
+        }
+    }
+
+    /// Applies the memory budget given to the builder to the cache, if any
+    #[cfg(feature = "serde")]
+    fn with_budget(&self, cache: DiffCache<C, usize, D>) -> DiffCache<C, usize, D> {
+        match self.memory_budget {
+            Some(ref budget) => budget.apply_to(cache),
+            None => cache,
+        }
+    }
+
+    /// Runs the user's fn to completion, without starting cursive (or reads
+    /// the frames of the opened snapshot or recording),
+    /// then exports all the frames to the requested formats
+    fn export(&self, frames: Frames<C, D>) -> io::Result<()>
+    where
+        C: Sync,
+        D: Sync,
+    {
+        let start = Instant::now();
+        match frames {
+            Frames::Run(input, mut recorder) => {
+                let mut recorded = Ok(());
+                populate(
+                    &self.workers,
+                    self.cache.clone(),
+                    self.time_index.clone(),
+                    (self.fn_user)(input),
+                    |index, item, changes| {
+                        record_breakpoint(
+                            &self.breakpoints,
+                            &self.time_index,
+                            index,
+                            item,
+                            changes,
+                        );
+                        if let Err(e) = record(&mut recorder, RecordEntry::Frame(index, changes)) {
+                            recorded = Err(e);
+                        }
+                    },
+                );
+                recorded?;
+                let generation = Generation::Finished(start.elapsed());
+                record(&mut recorder, RecordEntry::End(generation))?;
+            }
+            // Waits for the header of the recording to be written
+            Frames::Replay(mut source) => {
+                while source(&self.cache, &self.time_index)?.is_none() {
+                    std::thread::sleep(RECORDING_POLL_DELAY);
+                }
+            }
+            Frames::Loaded => (),
+        }
+        self.time_index
+            .lock()
+            .unwrap()
+            .finish_generation(start.elapsed());
+
+        let (min, max) = {
+            let time_index = self.time_index.lock().unwrap();
+            (time_index.min, time_index.max)
+        };
+        self.cache.sync();
+        let cache = self.cache.snapshot();
+        if let Some(ref path) = self.gif {
+            let file = BufWriter::new(File::create(path)?);
+            export_gif(&cache, min..=max, &GifOptions::default(), file)?;
+        }
+        if let Some(ref path) = self.cast {
+            let file = BufWriter::new(File::create(path)?);
+            export_asciicast(&cache, min..=max, &CastOptions::default(), file)?;
+        }
+        self.save_snapshot()
+    }
+
+    /// Fills the cache and the TimeIndex with the frames of the snapshot to
+    /// open, if any. Returns false if there is none.
+    #[cfg(feature = "serde")]
+    fn load_snapshot(&self) -> io::Result<bool> {
+        let path = match self.load {
+            Some(ref path) => path,
+            None => return Ok(false),
+        };
+        let (cache, time_index) = (self.snapshot_io()?.load)(path)?;
+        self.cache.replace(self.with_budget(cache));
+        *self.time_index.lock().unwrap() = time_index;
+        find_breakpoints(
+            &self.breakpoints,
+            &self.cache.snapshot(),
+            &self.time_index,
+            0,
+        );
+        Ok(true)
+    }
+
+    /// Saves a snapshot of the frames, if asked to
+    #[cfg(feature = "serde")]
+    fn save_snapshot(&self) -> io::Result<()> {
+        if let Some(ref path) = self.save {
+            let cache = self.cache.snapshot();
+            let time_index = self.time_index.lock().unwrap();
+            (self.snapshot_io()?.save)(&cache, &time_index, path)?;
+        }
+        Ok(())
+    }
+
+    /// Opens the recording to read the frames from, if any, starting from a
+    /// new cache with its default value once its header is read
+    #[cfg(feature = "serde")]
+    fn open_replay(&self) -> io::Result<Option<FrameSource<C, D>>> {
+        let path = match self.replay {
+            Some(ref path) => path,
+            None => return Ok(None),
+        };
+        let new_cache = Box::new(self.cache_builder());
+        (self.snapshot_io()?.replay)(path, new_cache).map(Some)
+    }
+
+    /// Creates the recording to write the frames to, if asked to
+    #[cfg(feature = "serde")]
+    fn create_recorder(&self) -> io::Result<Option<Recorder<C, D>>> {
+        let path = match self.record {
+            Some(ref path) => path,
+            None => return Ok(None),
+        };
+        let default = self.cache.snapshot().default_value().clone();
+        (self.snapshot_io()?.record)(path, &default).map(Some)
+    }
+
+    /// The way to save and load the snapshots, failing if it was not enabled
+    #[cfg(feature = "serde")]
+    fn snapshot_io(&self) -> io::Result<&SnapshotIo<C, D>> {
+        self.snapshots.as_ref().ok_or_else(|| {
+            io::Error::other(
+                "Snapshots are not enabled for this visualization (cf. AocVizApp::with_snapshots)",
+            )
+        })
+    }
+
+    /// Snapshots can not be opened without the serde feature
+    #[cfg(not(feature = "serde"))]
+    fn load_snapshot(&self) -> io::Result<bool> {
+        match self.load {
+            Some(_) => Err(snapshots_unsupported()),
+            None => Ok(false),
+        }
+    }
+
+    /// Snapshots can not be saved without the serde feature
+    #[cfg(not(feature = "serde"))]
+    fn save_snapshot(&self) -> io::Result<()> {
+        match self.save {
+            Some(_) => Err(snapshots_unsupported()),
+            None => Ok(()),
+        }
+    }
+
+    /// Recordings can not be opened without the serde feature
+    #[cfg(not(feature = "serde"))]
+    fn open_replay(&self) -> io::Result<Option<FrameSource<C, D>>> {
+        match self.replay {
+            Some(_) => Err(snapshots_unsupported()),
+            None => Ok(None),
+        }
+    }
+
+    /// Recordings can not be written without the serde feature
+    #[cfg(not(feature = "serde"))]
+    fn create_recorder(&self) -> io::Result<Option<Recorder<C, D>>> {
+        match self.record {
+            Some(_) => Err(snapshots_unsupported()),
+            None => Ok(None),
+        }
     }
 }
 
-struct CachePopulator<F> {
-    cache: Arc<Mutex<DiffCache<(i32, i32), usize, char>>>,
+#[cfg(feature = "serde")]
+impl<F, T, V, C, D> AocVizApp<F, T, V, C, D>
+where
+    F: Fn(String) -> T + Clone + Send + Sync + 'static,
+    T: Iterator<Item = V>,
+    V: Visualize<C, D> + std::fmt::Debug + std::default::Default + 'static,
+    C: Coords + Serialize + DeserializeOwned,
+    D: Displayable + std::fmt::Debug + Serialize + DeserializeOwned,
+{
+    /// Enables saving a snapshot of the frames, and opening it back instead of
+    /// running the user's fn, with the `--save` and `--load` arguments.
+    /// Also enables writing the frames to a recording as they are produced,
+    /// and replaying it, with the `--record` and `--replay` arguments
+    pub fn with_snapshots(mut self) -> Self {
+        self.snapshots = Some(SnapshotIo {
+            save: snapshot::save_file,
+            load: snapshot::load_file,
+            record: create_recording,
+            replay: open_recording,
+        });
+        self
+    }
+
+    /// Opens the frames saved in the snapshot at the given path instead of
+    /// running the user's fn, when launched
+    pub fn with_snapshot_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.load = Some(path.into());
+        self.with_snapshots()
+    }
+
+    /// Saves a snapshot of the frames to the given path instead of showing
+    /// them, when launched
+    pub fn with_snapshot_export(mut self, path: impl Into<PathBuf>) -> Self {
+        self.save = Some(path.into());
+        self.with_snapshots()
+    }
+
+    /// Writes each frame to the recording at the given path as soon as it is
+    /// produced by the user's fn, when launched
+    pub fn with_recording(mut self, path: impl Into<PathBuf>) -> Self {
+        self.record = Some(path.into());
+        self.with_snapshots()
+    }
+
+    /// Reads the frames from the recording at the given path instead of
+    /// running the user's fn, including the ones still being written, when launched
+    pub fn with_replay_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.replay = Some(path.into());
+        self.with_snapshots()
+    }
+}
+
+/// Saves and loads the snapshots of an AocVizApp, whose types can be serialized
+#[cfg(feature = "serde")]
+struct SnapshotIo<C: Coords, D> {
+    save: SaveSnapshot<C, D>,
+    load: LoadSnapshot<C, D>,
+    record: CreateRecorder<C, D>,
+    replay: OpenReplay<C, D>,
+}
+
+/// Saves the frames to a snapshot file
+#[cfg(feature = "serde")]
+type SaveSnapshot<C, D> = fn(&DiffCache<C, usize, D>, &TimeIndex, &Path) -> io::Result<()>;
+
+/// Loads the frames from a snapshot file
+#[cfg(feature = "serde")]
+type LoadSnapshot<C, D> = fn(&Path) -> io::Result<(DiffCache<C, usize, D>, TimeIndex)>;
+
+/// Creates a recording file, given the default value of the frames
+#[cfg(feature = "serde")]
+type CreateRecorder<C, D> = fn(&Path, &D) -> io::Result<Recorder<C, D>>;
+
+/// Opens a recording file, whose frames are read into a new cache built with
+/// their default value
+#[cfg(feature = "serde")]
+type OpenReplay<C, D> = fn(&Path, NewCache<C, D>) -> io::Result<FrameSource<C, D>>;
+
+/// Builds the cache to store the frames in, given their default value
+#[cfg(feature = "serde")]
+type NewCache<C, D> = Box<dyn FnOnce(D) -> DiffCache<C, usize, D> + Send>;
+
+/// Creates the recording at the given path (cf. `RecordingWriter`)
+#[cfg(feature = "serde")]
+fn create_recording<C, D>(path: &Path, default: &D) -> io::Result<Recorder<C, D>>
+where
+    C: Coords + Serialize,
+    D: Displayable + Serialize,
+{
+    let mut writer = RecordingWriter::create(path, default)?;
+    Ok(Box::new(move |entry| match entry {
+        RecordEntry::Frame(index, changes) => writer.write_frame(index, changes),
+        RecordEntry::End(generation) => writer.write_end(generation),
+    }))
+}
+
+/// Opens the recording at the given path (cf. `RecordingReader`).
+/// Its header may not be written yet, in which case no frames are read
+/// until it is. The cache is then replaced by one built with the default
+/// value of the frames
+#[cfg(feature = "serde")]
+fn open_recording<C, D>(path: &Path, new_cache: NewCache<C, D>) -> io::Result<FrameSource<C, D>>
+where
+    C: Coords + DeserializeOwned,
+    D: Displayable + DeserializeOwned,
+{
+    let path = path.to_path_buf();
+    let open = move || match RecordingReader::<_, C, D>::open(&path) {
+        // The header is not written yet
+        Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(None),
+        opened => opened.map(Some),
+    };
+    let mut reader = open()?;
+    let mut new_cache = Some(new_cache);
+    Ok(Box::new(move |cache, time_index| {
+        if reader.is_none() {
+            reader = open()?;
+        }
+        let reader = match reader {
+            Some(ref mut reader) => reader,
+            None => return Ok(None),
+        };
+        if let Some(new_cache) = new_cache.take() {
+            cache.replace(new_cache(reader.default_value().clone()));
+        }
+        reader.read_into(cache, time_index).map(Some)
+    }))
+}
+
+/// What is appended to a recording, which is only written with the serde feature
+#[cfg_attr(not(feature = "serde"), allow(dead_code))]
+enum RecordEntry<'a, C, D> {
+    /// A frame, given its index and the values that changed
+    Frame(usize, &'a [(C, D)]),
+    /// The end of the run, once no more frames will come
+    End(Generation),
+}
+
+/// Appends an entry to a recording
+type Recorder<C, D> = Box<dyn FnMut(RecordEntry<'_, C, D>) -> io::Result<()> + Send>;
+
+/// Reads the frames appended to a recording since the last call into the
+/// cache and the TimeIndex, returning their number, or None while the
+/// header of the recording is not written yet
+type FrameSource<C, D> =
+    Box<dyn FnMut(&SharedDiffCache<C, D>, &Mutex<TimeIndex>) -> io::Result<Option<usize>> + Send>;
+
+/// Memory the frames may use, applied to the cache once built at launch
+struct MemoryBudget<C: Coords, D> {
+    bytes: usize,
+    apply: ApplyBudget<C, D>,
+}
+
+impl<C: Coords, D> MemoryBudget<C, D> {
+    /// Applies the budget to a cache. The SharedDiffCache keeps two copies
+    /// of it, which share the budget
+    fn apply_to(&self, cache: DiffCache<C, usize, D>) -> DiffCache<C, usize, D> {
+        (self.apply)(cache, self.bytes / 2)
+    }
+}
+
+impl<C: Coords, D> Clone for MemoryBudget<C, D> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<C: Coords, D> Copy for MemoryBudget<C, D> {}
+
+/// Sets the memory budget of a cache (cf. `DiffCache::with_memory_budget`)
+type ApplyBudget<C, D> = fn(DiffCache<C, usize, D>, usize) -> DiffCache<C, usize, D>;
+
+/// Computes the changes between the states of the user's fn on worker threads
+struct Workers<V, C: Coords, D> {
+    count: usize,
+    populate: PopulateInParallel<V, C, D>,
+}
+
+/// Populates the cache from the states of the user's fn with the given
+/// number of workers, calling back on each frame (cf. `populate_cache_parallel`)
+type PopulateInParallel<V, C, D> = fn(
+    SharedDiffCache<C, D>,
+    Arc<Mutex<TimeIndex>>,
+    &mut dyn Iterator<Item = V>,
+    usize,
+    &mut OnFrame<'_, V, C, D>,
+);
+
+/// Called once each frame is computed, before it is stored, with its index,
+/// the state it comes from and the values that changed
+type OnFrame<'a, V, C, D> = dyn FnMut(usize, &V, &[(C, D)]) + 'a;
+
+/// Populates the cache with `populate_cache_parallel`, for the states that
+/// can be shared between threads
+fn populate_in_parallel<V, C, D>(
+    cache: SharedDiffCache<C, D>,
+    time_index: Arc<Mutex<TimeIndex>>,
+    states: &mut dyn Iterator<Item = V>,
+    workers: usize,
+    on_frame: &mut OnFrame<'_, V, C, D>,
+) where
+    V: Visualize<C, D> + std::fmt::Debug + std::default::Default + Send + Sync,
+    C: Coords + Sync,
+    D: Displayable + std::fmt::Debug + Sync,
+{
+    populate_cache_parallel(cache, time_index, states, workers, on_frame);
+}
+
+/// Populates the cache from the states of the user's fn, on the workers if any
+fn populate<V, C, D>(
+    workers: &Option<Workers<V, C, D>>,
+    cache: SharedDiffCache<C, D>,
+    time_index: Arc<Mutex<TimeIndex>>,
+    mut states: impl Iterator<Item = V>,
+    mut on_frame: impl FnMut(usize, &V, &[(C, D)]),
+) where
+    V: Visualize<C, D> + std::fmt::Debug + std::default::Default,
+    C: Coords + Sync,
+    D: Displayable + std::fmt::Debug + Sync,
+{
+    match workers {
+        Some(workers) => {
+            (workers.populate)(cache, time_index, &mut states, workers.count, &mut on_frame)
+        }
+        None => populate_cache_with(cache, time_index, states, on_frame),
+    }
+}
+
+/// Error happening while launching an AocVizApp
+#[derive(Debug)]
+pub enum LaunchError {
+    /// The command-line arguments are not valid
+    Args(ArgsError),
+    /// The frames could not be read, or the visualization could not be exported
+    Io(io::Error),
+}
+
+impl LaunchError {
+    /// Prints the error to stderr, followed by the usage if the arguments are
+    /// not valid, then exits with status 2 for invalid arguments, 1 otherwise.
+    /// To be called from the `main` function of a visualizer binary
+    pub fn exit(self) -> ! {
+        match self {
+            LaunchError::Args(e) => {
+                eprintln!("{}\n\n{}", e, USAGE);
+                std::process::exit(2)
+            }
+            LaunchError::Io(e) => {
+                eprintln!("{}", e);
+                std::process::exit(1)
+            }
+        }
+    }
+}
+
+impl fmt::Display for LaunchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LaunchError::Args(e) => write!(f, "{}", e),
+            LaunchError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for LaunchError {}
+
+impl From<ArgsError> for LaunchError {
+    fn from(e: ArgsError) -> Self {
+        LaunchError::Args(e)
+    }
+}
+
+impl From<io::Error> for LaunchError {
+    fn from(e: io::Error) -> Self {
+        LaunchError::Io(e)
+    }
+}
+
+/// Where the frames shown by an AocVizApp come from
+enum Frames<C: Coords, D> {
+    /// Running the user's fn on this input, writing the frames to the recorder if any
+    Run(String, Option<Recorder<C, D>>),
+    /// Reading a recording, possibly still being written
+    Replay(FrameSource<C, D>),
+    /// Nothing left to read, the frames were loaded from a snapshot
+    Loaded,
+}
+
+/// Error for the snapshot arguments, given to a build without the serde feature
+#[cfg(not(feature = "serde"))]
+fn snapshots_unsupported() -> io::Error {
+    io::Error::other("Snapshots require building aoc_viz with the serde feature")
+}
+
+/// Appends the entry to the recording, if any.
+/// The recorder is dropped at the first entry that can not be written.
+fn record<C, D>(
+    recorder: &mut Option<Recorder<C, D>>,
+    entry: RecordEntry<'_, C, D>,
+) -> io::Result<()> {
+    let result = match recorder {
+        Some(record) => record(entry),
+        None => return Ok(()),
+    };
+    if result.is_err() {
+        *recorder = None;
+    }
+    result
+}
+
+/// Records the first Breakpoint hit by the frame in the TimeIndex, if any
+fn record_breakpoint<C, V, D>(
+    breakpoints: &[Breakpoint<C, V, D>],
+    time_index: &Mutex<TimeIndex>,
+    index: usize,
+    item: &V,
+    changes: &[(C, D)],
+) {
+    if let Some(hit) = breakpoints.iter().find(|b| b.is_hit(item, changes)) {
+        time_index
+            .lock()
+            .unwrap()
+            .hit_breakpoint(index, hit.name.clone());
+    }
+}
+
+/// Records the hits of the Breakpoints on changes in the frames from the
+/// given index, read from a snapshot or a recording: the states of the user's
+/// fn are unknown. Each Breakpoint reads the changes from that index once
+fn find_breakpoints<C, V, D>(
+    breakpoints: &[Breakpoint<C, V, D>],
+    cache: &DiffCache<C, usize, D>,
+    time_index: &Mutex<TimeIndex>,
+    from: usize,
+) where
+    C: Coords,
+{
+    breakpoints.iter().for_each(|breakpoint| {
+        let mut from = from;
+        while let Some(index) = breakpoint.find_in(cache, from) {
+            time_index
+                .lock()
+                .unwrap()
+                .hit_breakpoint(index, breakpoint.name.clone());
+            from = index + 1;
+        }
+    });
+}
+
+struct CachePopulator<F, V, C: Coords, D> {
+    cache: SharedDiffCache<C, D>,
     fn_user: Box<F>,
     time_index: Arc<Mutex<TimeIndex>>,
+    input: String,
+    /// Checked on every frame, their hits are recorded in the TimeIndex
+    breakpoints: Vec<Breakpoint<C, V, D>>,
+    /// Writes the frames to a recording as they are produced, if any
+    recorder: Option<Recorder<C, D>>,
+    /// Computes the changes between the states, if any
+    workers: Option<Workers<V, C, D>>,
+    /// Used to notify cursive once the generation is over
+    cb_sink: Sender<Box<dyn CbFunc>>,
 }
 
-impl<F, T, V> CachePopulator<F>
+impl<F, T, V, C, D> CachePopulator<F, V, C, D>
 where
     T: Iterator<Item = V>,
-    V: Visualize<(i32, i32), char> + std::fmt::Debug + std::default::Default,
+    V: Visualize<C, D> + std::fmt::Debug + std::default::Default + 'static,
     F: Fn(String) -> T + Send + Sync + 'static,
+    C: Coords,
+    D: Displayable + std::fmt::Debug,
 {
     pub fn new(
-        cache: Arc<Mutex<DiffCache<(i32, i32), usize, char>>>,
+        cache: SharedDiffCache<C, D>,
         fn_user: Box<F>,
         time_index: Arc<Mutex<TimeIndex>>,
+        input: String,
+        breakpoints: Vec<Breakpoint<C, V, D>>,
+        recorder: Option<Recorder<C, D>>,
+        cb_sink: Sender<Box<dyn CbFunc>>,
     ) -> Self {
         CachePopulator {
             cache,
             fn_user,
             time_index,
+            input,
+            breakpoints,
+            recorder,
+            workers: None,
+            cb_sink,
+        }
+    }
+
+    /// Computes the changes between the states on the given workers, if any
+    pub fn with_workers(mut self, workers: Option<Workers<V, C, D>>) -> Self {
+        self.workers = workers;
+        self
+    }
+
+    pub fn launch(mut self)
+    where
+        C: Sync,
+        D: Sync,
+    {
+        std::thread::spawn(move || {
+            let mut end = GenerationEnd {
+                time_index: self.time_index.clone(),
+                recorder: self.recorder.take(),
+                cb_sink: self.cb_sink.clone(),
+                start: Instant::now(),
+            };
+            let (breakpoints, time_index) = (&self.breakpoints, &self.time_index);
+            let recorder = &mut end.recorder;
+            populate(
+                &self.workers,
+                self.cache,
+                self.time_index.clone(),
+                (self.fn_user)(self.input),
+                |index, item, changes| {
+                    record_breakpoint(breakpoints, time_index, index, item, changes);
+                    // The UI keeps showing the frames if the recording fails
+                    let _ = record(recorder, RecordEntry::Frame(index, changes));
+                },
+            );
+        });
+    }
+}
+
+/// Marks the generation as over once dropped, as failed if the user's fn
+/// panicked, so that the UI and the readers of the recording stop waiting
+/// for more frames
+struct GenerationEnd<C, D> {
+    time_index: Arc<Mutex<TimeIndex>>,
+    /// Writes the frames to a recording as they are produced, if any
+    recorder: Option<Recorder<C, D>>,
+    /// Used to notify cursive once the generation is over
+    cb_sink: Sender<Box<dyn CbFunc>>,
+    start: Instant,
+}
+
+impl<C, D> Drop for GenerationEnd<C, D> {
+    fn drop(&mut self) {
+        let generation = if std::thread::panicking() {
+            Generation::Failed(self.start.elapsed())
+        } else {
+            Generation::Finished(self.start.elapsed())
+        };
+        // The panic may have poisoned the lock, the TimeIndex stays usable
+        self.time_index
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .generation = generation;
+        let _ = record(&mut self.recorder, RecordEntry::End(generation));
+
+        // No need to redraw regularly anymore
+        let _ = self.cb_sink.send(Box::new(|c: &mut Cursive| c.set_fps(0)));
+    }
+}
+
+/// Theme of the UI, embedded so that binaries run from any directory
+const DEFAULT_THEME: &str = include_str!("../assets/default_theme.toml");
+
+/// Redraw rate of the UI while the frames are being generated
+const GENERATION_FPS: u32 = 30;
+
+/// Delay between two checks of the playback state while it is paused
+const PLAYER_IDLE_DELAY: Duration = Duration::from_millis(50);
+
+/// Delay between two reads of a recording once all its frames were read
+const RECORDING_POLL_DELAY: Duration = Duration::from_millis(100);
+
+/// Reads the frames appended to a recording while it is shown
+struct RecordingTail<V, C: Coords, D> {
+    cache: SharedDiffCache<C, D>,
+    time_index: Arc<Mutex<TimeIndex>>,
+    source: FrameSource<C, D>,
+    /// Searched in the frames read, their hits are recorded in the TimeIndex
+    breakpoints: Vec<Breakpoint<C, V, D>>,
+    /// Used to wake cursive up so that it redraws the new frames
+    cb_sink: Sender<Box<dyn CbFunc>>,
+}
+
+impl<V: 'static, C: Coords, D: Displayable> RecordingTail<V, C, D> {
+    pub fn new(
+        cache: SharedDiffCache<C, D>,
+        time_index: Arc<Mutex<TimeIndex>>,
+        source: FrameSource<C, D>,
+        breakpoints: Vec<Breakpoint<C, V, D>>,
+        cb_sink: Sender<Box<dyn CbFunc>>,
+    ) -> Self {
+        RecordingTail {
+            cache,
+            time_index,
+            source,
+            breakpoints,
+            cb_sink,
+        }
+    }
+
+    pub fn launch(mut self)
+    where
+        C: Sync,
+        D: Sync,
+    {
+        std::thread::spawn(move || loop {
+            let from = self.time_index.lock().unwrap().max + 1;
+            let read = match (self.source)(&self.cache, &self.time_index) {
+                Ok(read) => read.unwrap_or(0),
+                // Stops at the first frame that can not be read
+                Err(_) => return,
+            };
+            if read > 0 {
+                let cache = self.cache.snapshot();
+                find_breakpoints(&self.breakpoints, &cache, &self.time_index, from);
+            }
+            let ended = self.time_index.lock().unwrap().generation != Generation::Running;
+            // Stops when cursive is not listening anymore
+            if (read > 0 || ended) && self.cb_sink.send(Box::new(|_: &mut Cursive| ())).is_err() {
+                return;
+            }
+            // Stops once the recording ended, waits for more frames otherwise
+            if ended {
+                return;
+            } else if read == 0 {
+                std::thread::sleep(RECORDING_POLL_DELAY);
+            }
+        });
+    }
+}
+
+/// Advances the TimeIndex on a timer, following its `Playback` state
+struct Player {
+    time_index: Arc<Mutex<TimeIndex>>,
+    /// Used to wake cursive up so that it redraws the new time frame
+    cb_sink: Sender<Box<dyn CbFunc>>,
+}
+
+impl Player {
+    pub fn new(time_index: Arc<Mutex<TimeIndex>>, cb_sink: Sender<Box<dyn CbFunc>>) -> Self {
+        Player {
+            time_index,
+            cb_sink,
         }
     }
 
     pub fn launch(self) {
-        std::thread::spawn(|| {
-            let input: String = "abc".into();
-            populate_cache(self.cache, self.time_index, (self.fn_user)(input));
+        std::thread::spawn(move || loop {
+            let (changed, delay) = {
+                let mut time_index = self.time_index.lock().unwrap();
+                let delay = if time_index.playback.playing {
+                    Duration::from_millis(1000 / u64::from(time_index.playback.fps))
+                } else {
+                    PLAYER_IDLE_DELAY
+                };
+                (time_index.tick(), delay)
+            };
+
+            // Stops when cursive is not listening anymore
+            if changed && self.cb_sink.send(Box::new(|_: &mut Cursive| ())).is_err() {
+                return;
+            }
+            std::thread::sleep(delay);
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(input: String) -> std::vec::IntoIter<String> {
+        input
+            .lines()
+            .map(String::from)
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
+
+    #[test]
+    pub fn aoc_viz_app_check_args() {
+        let args = |args: &[&str]| Args::parse(args.iter().map(|a| a.to_string())).unwrap();
+        assert_eq!(
+            AocVizApp::from_args(lines, args(&["--gif", "a.gif"])).check_args(),
+            Ok(())
+        );
+        assert_eq!(
+            AocVizApp::from_args(lines, args(&["--replay", "run.rec"])).check_args(),
+            Err(ArgsError::SnapshotsDisabled("--replay".to_string()))
+        );
+        #[cfg(feature = "serde")]
+        assert_eq!(
+            AocVizApp::from_args(lines, args(&["--save", "run.snap"]))
+                .with_snapshots()
+                .check_args(),
+            Ok(())
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    pub fn aoc_viz_app_load_breakpoints() {
+        let mut cache = DiffCache::new(' ');
+        let infos = vec![((0, 0), 1, 'a'), ((1, 0), 2, 'b'), ((1, 0), 4, 'b')];
+        cache.append(infos.into_iter()).unwrap();
+        let path =
+            std::env::temp_dir().join(format!("aoc_viz_breakpoints_{}.snap", std::process::id()));
+        snapshot::save_file(&cache, &TimeIndex::new(0, 4, 0), &path).unwrap();
+
+        // The changes of a snapshot are searched for hits when it is loaded
+        let app = AocVizApp::new(lines)
+            .with_snapshots()
+            .with_snapshot_file(&path)
+            .with_breakpoint(Breakpoint::cell_becomes((1, 0), 'b'));
+        let loaded = app.load_snapshot();
+        std::fs::remove_file(&path).unwrap();
+        assert!(loaded.unwrap());
+        let breaks: Vec<usize> = app
+            .time_index
+            .lock()
+            .unwrap()
+            .breaks
+            .keys()
+            .copied()
+            .collect();
+        assert_eq!(breaks, vec![2, 4]);
+    }
+
+    #[test]
+    pub fn aoc_viz_app_launch_errors() {
+        let args = |args: &[&str]| Args::parse(args.iter().map(|a| a.to_string())).unwrap();
+        let launched = AocVizApp::from_args(lines, args(&["--gif", "missing/dir/a.gif"])).launch();
+        assert!(matches!(launched, Err(LaunchError::Io(_))));
+        let launched =
+            AocVizApp::from_args(lines, args(&["missing/input.txt", "--gif", "a.gif"])).launch();
+        assert!(matches!(launched, Err(LaunchError::Io(_))));
+        #[cfg(feature = "serde")]
+        {
+            let launched = AocVizApp::from_args(lines, args(&["--load", "missing.snap"]))
+                .with_snapshots()
+                .launch();
+            assert!(matches!(launched, Err(LaunchError::Io(_))));
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    pub fn aoc_viz_app_replay_unwritten() {
+        let path =
+            std::env::temp_dir().join(format!("aoc_viz_unwritten_{}.rec", std::process::id()));
+        File::create(&path).unwrap();
+        let cache = SharedDiffCache::new(DiffCache::new(' '));
+        let time_index = Mutex::new(TimeIndex::new(0, 0, 0));
+        let new_cache: NewCache<(i32, i32), char> = Box::new(DiffCache::new);
+        let mut source = open_recording(&path, new_cache).unwrap();
+
+        // Nothing is read until the header is written, the generation still running
+        let waiting = source(&cache, &time_index);
+        let mut writer = RecordingWriter::create(&path, &'.').unwrap();
+        writer.write_frame(1, &[((0, 0), '#')]).unwrap();
+        let read = source(&cache, &time_index);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(waiting.unwrap(), None);
+        assert_eq!(read.unwrap(), Some(1));
+        assert_eq!(time_index.lock().unwrap().generation, Generation::Running);
+        let cache = cache.snapshot();
+        assert_eq!(cache.default_value(), &'.');
+        assert_eq!(cache.search_or_default((0, 0), 1), &'#');
+    }
+}
@@ -0,0 +1,229 @@
+use crate::input::InputSource;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Usage of the command-line interface of an AocVizApp binary
+pub const USAGE: &str = "\
+Usage: <binary> [OPTIONS] [INPUT]
+
+Arguments:
+  INPUT              Path to the puzzle input, or `-` to read it from stdin
+
+Options:
+  --gif <PATH>       Exports the visualization to an animated GIF, without starting the UI
+  --cast <PATH>      Exports the visualization to an asciicast v2 recording, without starting the UI
+  --save <PATH>      Saves a snapshot of the frames, without starting the UI (requires the serde feature)
+  --load <PATH>      Opens the frames saved in a snapshot instead of running the visualization
+  --record <PATH>    Writes each frame to a recording as soon as it is produced (requires the serde feature)
+  --replay <PATH>    Opens a recording instead of running the visualization, reading its new frames as they are written
+  -y, --year <YEAR>  Year of the visualization to run, in a cargo-aoc project
+  -d, --day <DAY>    Day of the visualization to run, in a cargo-aoc project";
+
+/// Command-line arguments understood by an AocVizApp binary
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Args {
+    /// Where the puzzle input comes from, if specified
+    pub input: Option<InputSource>,
+    /// Path of the GIF to export, if any
+    pub gif: Option<PathBuf>,
+    /// Path of the asciicast recording to export, if any
+    pub cast: Option<PathBuf>,
+    /// Path of the snapshot to save, if any
+    pub save: Option<PathBuf>,
+    /// Path of the snapshot to open instead of running the visualization, if any
+    pub load: Option<PathBuf>,
+    /// Path of the recording to write while running the visualization, if any
+    pub record: Option<PathBuf>,
+    /// Path of the recording to open instead of running the visualization, if any
+    pub replay: Option<PathBuf>,
+    /// Year of the visualization to run, if any (cf. `AocRunner`)
+    pub year: Option<u32>,
+    /// Day of the visualization to run, if any (cf. `AocRunner`)
+    pub day: Option<u32>,
+}
+
+/// Error happening while parsing the command-line arguments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArgsError {
+    /// An option that expects a value was given none
+    MissingValue(String),
+    /// This option is not known
+    UnknownOption(String),
+    /// More than one input was given
+    TooManyInputs(String),
+    /// The value given to this option is not valid
+    InvalidValue(String, String),
+    /// This option reads or writes snapshots, which the visualization did
+    /// not enable (cf. `AocVizApp::with_snapshots`)
+    SnapshotsDisabled(String),
+}
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ArgsError::MissingValue(opt) => write!(f, "Missing value for option {}", opt),
+            ArgsError::UnknownOption(opt) => write!(f, "Unknown option {}", opt),
+            ArgsError::TooManyInputs(arg) => write!(f, "Unexpected argument {}", arg),
+            ArgsError::InvalidValue(opt, value) => {
+                write!(f, "Invalid value {} for option {}", value, opt)
+            }
+            ArgsError::SnapshotsDisabled(opt) => write!(
+                f,
+                "Option {} requires snapshots, which this visualization does not enable \
+                 (cf. the serde feature and AocVizApp::with_snapshots)",
+                opt
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ArgsError {}
+
+impl Args {
+    /// Parses the given arguments, not including the binary name.
+    ///
+    /// * The first non-option argument is considered a path to the input file,
+    /// * `-` means reading the input from stdin.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut parsed = Args::default();
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--gif" => match args.next() {
+                    Some(path) => parsed.gif = Some(path.into()),
+                    None => return Err(ArgsError::MissingValue(arg)),
+                },
+                "--cast" => match args.next() {
+                    Some(path) => parsed.cast = Some(path.into()),
+                    None => return Err(ArgsError::MissingValue(arg)),
+                },
+                "--save" => match args.next() {
+                    Some(path) => parsed.save = Some(path.into()),
+                    None => return Err(ArgsError::MissingValue(arg)),
+                },
+                "--load" => match args.next() {
+                    Some(path) => parsed.load = Some(path.into()),
+                    None => return Err(ArgsError::MissingValue(arg)),
+                },
+                "--record" => match args.next() {
+                    Some(path) => parsed.record = Some(path.into()),
+                    None => return Err(ArgsError::MissingValue(arg)),
+                },
+                "--replay" => match args.next() {
+                    Some(path) => parsed.replay = Some(path.into()),
+                    None => return Err(ArgsError::MissingValue(arg)),
+                },
+                "-y" | "--year" => parsed.year = Some(parse_number(&arg, args.next())?),
+                "-d" | "--day" => parsed.day = Some(parse_number(&arg, args.next())?),
+                _ if parsed.input.is_some() => return Err(ArgsError::TooManyInputs(arg)),
+                "-" => parsed.input = Some(InputSource::Stdin),
+                _ if arg.starts_with("--") => return Err(ArgsError::UnknownOption(arg)),
+                _ => parsed.input = Some(InputSource::File(arg.into())),
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Parses the arguments of the running binary
+    pub fn from_env() -> Result<Self, ArgsError> {
+        Args::parse(std::env::args().skip(1))
+    }
+}
+
+/// Parses the number given as the value of an option
+fn parse_number(opt: &str, value: Option<String>) -> Result<u32, ArgsError> {
+    let value = value.ok_or_else(|| ArgsError::MissingValue(opt.to_string()))?;
+    value
+        .parse()
+        .map_err(|_| ArgsError::InvalidValue(opt.to_string(), value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, ArgsError> {
+        Args::parse(args.iter().map(|a| a.to_string()))
+    }
+
+    #[test]
+    pub fn args_input() {
+        assert_eq!(parse(&[]).unwrap().input, None);
+        assert_eq!(parse(&["-"]).unwrap().input, Some(InputSource::Stdin));
+        assert_eq!(
+            parse(&["input.txt"]).unwrap().input,
+            Some(InputSource::File("input.txt".into()))
+        );
+    }
+
+    #[test]
+    pub fn args_gif() {
+        let args = parse(&["--gif", "out.gif", "input.txt"]).unwrap();
+        assert_eq!(args.gif, Some("out.gif".into()));
+        assert_eq!(args.input, Some(InputSource::File("input.txt".into())));
+
+        let args = parse(&["input.txt", "--gif", "out.gif"]).unwrap();
+        assert_eq!(args.gif, Some("out.gif".into()));
+        assert_eq!(args.cast, None);
+    }
+
+    #[test]
+    pub fn args_cast() {
+        let args = parse(&["--cast", "out.cast", "--gif", "out.gif"]).unwrap();
+        assert_eq!(args.cast, Some("out.cast".into()));
+        assert_eq!(args.gif, Some("out.gif".into()));
+        assert_eq!(args.input, None);
+    }
+
+    #[test]
+    pub fn args_snapshot() {
+        let args = parse(&["--save", "run.snap", "input.txt"]).unwrap();
+        assert_eq!(args.save, Some("run.snap".into()));
+        assert_eq!(args.load, None);
+        let args = parse(&["--load", "run.snap"]).unwrap();
+        assert_eq!(args.load, Some("run.snap".into()));
+        assert_eq!(args.input, None);
+    }
+
+    #[test]
+    pub fn args_recording() {
+        let args = parse(&["input.txt", "--record", "run.rec"]).unwrap();
+        assert_eq!(args.record, Some("run.rec".into()));
+        assert_eq!(args.input, Some(InputSource::File("input.txt".into())));
+        let args = parse(&["--replay", "run.rec"]).unwrap();
+        assert_eq!((args.replay, args.record), (Some("run.rec".into()), None));
+    }
+
+    #[test]
+    pub fn args_day() {
+        let args = parse(&["-y", "2018", "--day", "1"]).unwrap();
+        assert_eq!(args.year, Some(2018));
+        assert_eq!(args.day, Some(1));
+        assert_eq!(args.input, None);
+    }
+
+    #[test]
+    pub fn args_errors() {
+        assert_eq!(
+            parse(&["--gif"]),
+            Err(ArgsError::MissingValue("--gif".into()))
+        );
+        assert_eq!(
+            parse(&["--nope"]),
+            Err(ArgsError::UnknownOption("--nope".into()))
+        );
+        assert_eq!(
+            parse(&["a.txt", "b.txt"]),
+            Err(ArgsError::TooManyInputs("b.txt".into()))
+        );
+        assert_eq!(
+            parse(&["--day"]),
+            Err(ArgsError::MissingValue("--day".into()))
+        );
+        assert_eq!(
+            parse(&["-d", "one"]),
+            Err(ArgsError::InvalidValue("-d".into(), "one".into()))
+        );
+    }
+}
@@ -0,0 +1,59 @@
+//! `cargo aoc-viz` subcommand.
+//!
+//! Runs the visualizations of the cargo-aoc project containing the current
+//! directory. They are registered with an `AocRunner` in the `aoc-viz` binary
+//! of the project, to which the arguments are forwarded, e.g.
+//! `cargo aoc-viz -y 2018 -d 1 --gif day1.gif`. Without such a binary, one is
+//! suggested for the `#[aoc]` solutions found in the project.
+
+use aoc_viz::cargo_aoc::{AocProject, RUNNER_BIN};
+use std::env;
+use std::path::{Path, PathBuf};
+use std::process::{exit, Command};
+
+/// Finds the root of the cargo project containing the given directory
+fn project_root(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .find(|d| d.join("Cargo.toml").is_file())
+        .map(Path::to_path_buf)
+}
+
+fn main() {
+    // Cargo calls subcommands as `cargo-aoc-viz aoc-viz <args>`
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("aoc-viz") {
+        args.remove(0);
+    }
+
+    let current_dir = env::current_dir().expect("Failed to get the current directory");
+    let root = project_root(&current_dir).unwrap_or_else(|| {
+        eprintln!(
+            "Could not find a cargo project in {}",
+            current_dir.display()
+        );
+        exit(1);
+    });
+    let project = AocProject::discover(&root).unwrap_or_else(|e| {
+        eprintln!("Could not read the project in {}: {}", root.display(), e);
+        exit(1);
+    });
+    if !project.has_runner {
+        eprintln!(
+            "{} has no `{}` binary registering the visualizations. \
+             For the days it solves, with a `visualize` fn next to each solution:\n\n{}",
+            root.display(),
+            RUNNER_BIN,
+            project.runner_example()
+        );
+        exit(1);
+    }
+
+    // Runs from the root so that the input cache of cargo-aoc is found
+    let status = Command::new(env::var("CARGO").unwrap_or_else(|_| "cargo".to_string()))
+        .current_dir(&root)
+        .args(["run", "--release", "--bin", RUNNER_BIN, "--"])
+        .args(&args)
+        .status()
+        .expect("Failed to run cargo");
+    exit(status.code().unwrap_or(1));
+}
@@ -0,0 +1,134 @@
+use crate::coords::{Bounds, Coords};
+use crate::diff_cache::DiffCache;
+use std::fmt::Debug;
+
+/// Predicate over a cell that changed, and its new value
+type ChangePredicate<C, V> = Box<dyn Fn(&C, &V) -> bool + Send>;
+
+/// What a Breakpoint checks
+enum Condition<C, T, V> {
+    /// Checked on every cell that changed in the frame
+    Change(ChangePredicate<C, V>),
+    /// Checked on the state yielded by the user's fn for the frame
+    Item(Box<dyn Fn(&T) -> bool + Send>),
+}
+
+/// Condition checked on every frame produced by the user's fn, of type T,
+/// showing values of type V on a plane of C coordinates.
+/// The playback and the follow mode stop at the frames where it holds.
+pub struct Breakpoint<C, T, V> {
+    /// Description of the Breakpoint, shown when it is hit
+    pub name: String,
+    condition: Condition<C, T, V>,
+}
+
+impl<C, T, V> Breakpoint<C, T, V> {
+    /// Creates a Breakpoint hit when a cell changes to a value matching the predicate
+    pub fn on_change(
+        name: impl Into<String>,
+        predicate: impl Fn(&C, &V) -> bool + Send + 'static,
+    ) -> Self {
+        Breakpoint {
+            name: name.into(),
+            condition: Condition::Change(Box::new(predicate)),
+        }
+    }
+
+    /// Creates a Breakpoint hit when the state yielded by the user's fn
+    /// matches the predicate
+    pub fn on_item(
+        name: impl Into<String>,
+        predicate: impl Fn(&T) -> bool + Send + 'static,
+    ) -> Self {
+        Breakpoint {
+            name: name.into(),
+            condition: Condition::Item(Box::new(predicate)),
+        }
+    }
+
+    /// Is the Breakpoint hit by the given frame, made of the state yielded by
+    /// the user's fn and of the cells that changed ?
+    pub fn is_hit(&self, item: &T, changes: &[(C, V)]) -> bool {
+        match self.condition {
+            Condition::Change(ref predicate) => changes.iter().any(|(c, v)| predicate(c, v)),
+            Condition::Item(ref predicate) => predicate(item),
+        }
+    }
+
+    /// Searches the cache for the first index, starting from the given one,
+    /// at which the Breakpoint is hit.
+    /// Returns None for Breakpoints checking the states of the user's fn,
+    /// as they are not stored.
+    pub fn find_in(&self, cache: &DiffCache<C, usize, V>, from: usize) -> Option<usize>
+    where
+        C: Coords,
+    {
+        match self.condition {
+            Condition::Change(ref predicate) => cache.find_change(from, predicate),
+            Condition::Item(_) => None,
+        }
+    }
+}
+
+impl<C, T, V> Breakpoint<C, T, V>
+where
+    C: Coords,
+    V: PartialEq + Debug + Send + 'static,
+{
+    /// Creates a Breakpoint hit when the cell at the given coordinates becomes the value
+    pub fn cell_becomes(coords: C, value: V) -> Self {
+        let name = format!("{:?} becomes {:?}", coords, value);
+        Breakpoint::on_change(name, move |c, v| *c == coords && *v == value)
+    }
+
+    /// Creates a Breakpoint hit when any cell outside of the bounds becomes the value
+    pub fn value_outside(value: V, bounds: Bounds) -> Self {
+        let name = format!("{:?} outside {:?}..={:?}", value, bounds.min, bounds.max);
+        Breakpoint::on_change(name, move |c, v| *v == value && !bounds.contains(c))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn breakpoint_cell_becomes() {
+        let breakpoint: Breakpoint<(i32, i32), (), char> = Breakpoint::cell_becomes((1, 2), '#');
+        assert_eq!(breakpoint.name, "(1, 2) becomes '#'");
+        assert!(breakpoint.is_hit(&(), &[((0, 0), '.'), ((1, 2), '#')]));
+        assert!(!breakpoint.is_hit(&(), &[((1, 2), '.'), ((0, 0), '#')]));
+        assert!(!breakpoint.is_hit(&(), &[]));
+    }
+
+    #[test]
+    pub fn breakpoint_value_outside() {
+        let mut bounds = Bounds::new(0, 0);
+        bounds.include(9, 9);
+        let breakpoint: Breakpoint<(i32, i32), (), char> = Breakpoint::value_outside('@', bounds);
+        assert!(breakpoint.is_hit(&(), &[((10, 0), '@')]));
+        assert!(!breakpoint.is_hit(&(), &[((9, 9), '@')]));
+        assert!(!breakpoint.is_hit(&(), &[((-1, 0), '#')]));
+    }
+
+    #[test]
+    pub fn breakpoint_on_item() {
+        let breakpoint: Breakpoint<(i32, i32), u32, char> =
+            Breakpoint::on_item("above 10", |item| *item > 10);
+        assert!(breakpoint.is_hit(&11, &[]));
+        assert!(!breakpoint.is_hit(&3, &[((0, 0), '#')]));
+    }
+
+    #[test]
+    pub fn breakpoint_find_in() {
+        let mut cache: DiffCache<(i32, i32), usize, char> = DiffCache::new('.');
+        let infos = vec![((0, 0), 1, '#'), ((1, 2), 3, '#'), ((1, 2), 7, '#')];
+        cache.append(infos.into_iter()).unwrap();
+
+        let breakpoint: Breakpoint<(i32, i32), (), char> = Breakpoint::cell_becomes((1, 2), '#');
+        assert_eq!(breakpoint.find_in(&cache, 0), Some(3));
+        assert_eq!(breakpoint.find_in(&cache, 4), Some(7));
+        let breakpoint: Breakpoint<(i32, i32), (), char> = Breakpoint::on_item("any", |_| true);
+        assert_eq!(breakpoint.find_in(&cache, 0), None);
+    }
+}
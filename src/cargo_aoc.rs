@@ -0,0 +1,412 @@
+use crate::aoc_viz_app::{AocVizApp, LaunchError};
+use crate::args::{Args, ArgsError, USAGE};
+use crate::cell::Displayable;
+use crate::coords::Coords;
+use crate::visualize::Visualize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Name of the binary of a cargo-aoc project registering its visualizations
+pub const RUNNER_BIN: &str = "aoc-viz";
+
+/// A puzzle of a cargo-aoc project
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AocDay {
+    pub year: u32,
+    pub day: u32,
+}
+
+impl AocDay {
+    /// Creates a new instance of an AocDay
+    pub fn new(year: u32, day: u32) -> Self {
+        AocDay { year, day }
+    }
+
+    /// Path of the input of this day, as cached by cargo-aoc
+    /// relatively to the root of the project
+    pub fn input_path(&self) -> PathBuf {
+        PathBuf::from(format!("input/{}/day{}.txt", self.year, self.day))
+    }
+}
+
+impl fmt::Display for AocDay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} day {}", self.year, self.day)
+    }
+}
+
+/// Launches an AocVizApp with the given arguments
+type Launcher = Box<dyn Fn(Args) -> Result<(), LaunchError>>;
+
+/// Error happening while selecting the visualization to run, or launching it
+#[derive(Debug)]
+pub enum RunnerError {
+    /// The command-line arguments are not valid
+    Args(ArgsError),
+    /// No visualization is registered for the selected day, but for these ones
+    UnknownDay(Vec<AocDay>),
+    /// The frames could not be read, or the visualization could not be exported
+    Io(io::Error),
+}
+
+impl fmt::Display for RunnerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RunnerError::Args(e) => write!(f, "{}", e),
+            RunnerError::UnknownDay(days) => {
+                let days: Vec<String> = days.iter().map(ToString::to_string).collect();
+                write!(
+                    f,
+                    "No visualization registered for this day. Registered days: {}",
+                    days.join(", ")
+                )
+            }
+            RunnerError::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for RunnerError {}
+
+impl From<ArgsError> for RunnerError {
+    fn from(e: ArgsError) -> Self {
+        RunnerError::Args(e)
+    }
+}
+
+impl From<LaunchError> for RunnerError {
+    fn from(e: LaunchError) -> Self {
+        match e {
+            LaunchError::Args(e) => RunnerError::Args(e),
+            LaunchError::Io(e) => RunnerError::Io(e),
+        }
+    }
+}
+
+impl RunnerError {
+    /// Prints the error to stderr, followed by the usage unless the
+    /// visualization failed to read or export its frames, then exits with
+    /// status 1 for these, 2 otherwise.
+    /// To be called from the `main` function of the runner binary
+    pub fn exit(self) -> ! {
+        match self {
+            RunnerError::Io(e) => LaunchError::Io(e).exit(),
+            e => {
+                eprintln!("{}\n\n{}", e, USAGE);
+                std::process::exit(2)
+            }
+        }
+    }
+}
+
+/// Runs the visualization registered for the day chosen on the command-line,
+/// in a cargo-aoc project.
+///
+/// The day is selected with the `--year` and `--day` arguments, defaulting to
+/// the latest registered one. Its input is read from the cargo-aoc input cache
+/// (`input/<year>/day<N>.txt`), unless another input is given.
+#[derive(Default)]
+pub struct AocRunner {
+    days: BTreeMap<AocDay, Launcher>,
+}
+
+impl AocRunner {
+    /// Creates a new instance of an AocRunner, with no registered visualization
+    pub fn new() -> Self {
+        AocRunner::default()
+    }
+
+    /// Registers the user's fn as the visualization of the given day
+    pub fn with_day<F, T, V, C, D>(mut self, year: u32, day: u32, fn_user: F) -> Self
+    where
+        F: Fn(String) -> T + Clone + Send + Sync + 'static,
+        T: Iterator<Item = V> + 'static,
+        V: Visualize<C, D> + std::fmt::Debug + std::default::Default + 'static,
+        C: Coords + Sync,
+        D: Displayable + std::fmt::Debug + Sync,
+    {
+        let aoc_day = AocDay::new(year, day);
+        let launcher = move |args: Args| {
+            AocVizApp::from_args(fn_user.clone(), args)
+                .with_default_input_file(aoc_day.input_path())
+                .launch()
+        };
+        self.days.insert(aoc_day, Box::new(launcher));
+        self
+    }
+
+    /// Registered days, in chronological order
+    pub fn days(&self) -> impl Iterator<Item = &AocDay> {
+        self.days.keys()
+    }
+
+    /// Latest registered day matching the given year and day, if specified
+    pub fn select(&self, year: Option<u32>, day: Option<u32>) -> Option<AocDay> {
+        self.days
+            .keys()
+            .rev()
+            .find(|d| year.map_or(true, |y| d.year == y) && day.map_or(true, |n| d.day == n))
+            .cloned()
+    }
+
+    /// Runs the visualization of the day selected by the command-line arguments.
+    /// Fails if they are invalid, or if there is no visualization matching.
+    pub fn launch(self) -> Result<(), RunnerError> {
+        let args = Args::from_env()?;
+        let day = self
+            .select(args.year, args.day)
+            .ok_or_else(|| RunnerError::UnknownDay(self.days().cloned().collect()))?;
+        Ok((self.days[&day])(args)?)
+    }
+}
+
+/// Layout of a cargo-aoc project, as found by `AocProject::discover`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AocProject {
+    /// Name of the crate, as written in paths
+    pub crate_name: String,
+    /// Year of the puzzles, from `aoc_lib! { year = <YEAR> }`, if found
+    pub year: Option<u32>,
+    /// Days solved with `#[aoc(day<N>, ...)]` or `#[aoc_generator(day<N>)]`,
+    /// with the path of the module solving them
+    pub days: BTreeMap<u32, String>,
+    /// Does the project have a binary registering the visualizations ?
+    pub has_runner: bool,
+}
+
+impl AocProject {
+    /// Reads the manifest and the sources of the cargo-aoc project at the
+    /// given root, finding its year, its solved days and its runner binary
+    pub fn discover(root: &Path) -> io::Result<Self> {
+        let manifest = fs::read_to_string(root.join("Cargo.toml"))?;
+        let (crate_name, bins) = read_manifest(&manifest);
+        let src = root.join("src");
+        let has_runner = bins.iter().any(|b| b == RUNNER_BIN)
+            || src.join("bin").join(format!("{}.rs", RUNNER_BIN)).is_file()
+            || src.join("bin").join(RUNNER_BIN).join("main.rs").is_file();
+
+        let mut project = AocProject {
+            crate_name: crate_name.unwrap_or_default().replace('-', "_"),
+            year: None,
+            days: BTreeMap::new(),
+            has_runner,
+        };
+        for path in rust_files(&src)? {
+            // Binaries are not part of the library solving the puzzles
+            if path.starts_with(src.join("bin")) {
+                continue;
+            }
+            let content = fs::read_to_string(&path)?;
+            project.year = project.year.or_else(|| lib_year(&content));
+            let module = module_path(path.strip_prefix(&src).unwrap_or(&path));
+            attribute_days(&content).for_each(|day| {
+                project.days.entry(day).or_insert_with(|| module.clone());
+            });
+        }
+        Ok(project)
+    }
+
+    /// Source of a runner binary registering a `visualize` fn for each solved day
+    pub fn runner_example(&self) -> String {
+        let year = self.year.map_or("<YEAR>".to_string(), |y| y.to_string());
+        let mut days: Vec<(u32, String)> = self
+            .days
+            .iter()
+            .map(|(day, module)| (*day, module.clone()))
+            .collect();
+        if days.is_empty() {
+            days.push((1, "day1".to_string()));
+        }
+        let with_days: String = days
+            .iter()
+            .map(|(day, module)| {
+                let path = match module.as_str() {
+                    "" => self.crate_name.clone(),
+                    module => format!("{}::{}", self.crate_name, module),
+                };
+                format!(
+                    "\n        .with_day({}, {}, {}::visualize)",
+                    year, day, path
+                )
+            })
+            .collect();
+        format!(
+            "// src/bin/{}.rs
+use aoc_viz::cargo_aoc::AocRunner;
+
+fn main() {{
+    let runner = AocRunner::new(){};
+    if let Err(e) = runner.launch() {{
+        e.exit();
+    }}
+}}",
+            RUNNER_BIN, with_days
+        )
+    }
+}
+
+/// Name of the package and names of the binaries declared by a manifest
+fn read_manifest(manifest: &str) -> (Option<String>, Vec<String>) {
+    let mut section = "";
+    let mut name = None;
+    let mut bins = Vec::new();
+    for line in manifest.lines().map(str::trim) {
+        if line.starts_with('[') {
+            section = line;
+            continue;
+        }
+        let value = match line.split_once('=') {
+            Some((key, value)) if key.trim() == "name" => value.trim().trim_matches('"'),
+            _ => continue,
+        };
+        match section {
+            "[package]" => name = Some(value.to_string()),
+            "[[bin]]" => bins.push(value.to_string()),
+            _ => (),
+        }
+    }
+    (name, bins)
+}
+
+/// Every Rust source file under the given directory, recursively
+fn rust_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(rust_files(&path)?);
+        } else if path.extension().is_some_and(|e| e == "rs") {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Path of the module defined by a source file, given relatively to `src`.
+/// Empty for the root of the crate
+fn module_path(file: &Path) -> String {
+    let mut parts: Vec<String> = file
+        .with_extension("")
+        .iter()
+        .map(|p| p.to_string_lossy().into_owned())
+        .collect();
+    if let Some("lib") | Some("main") | Some("mod") = parts.last().map(String::as_str) {
+        parts.pop();
+    }
+    parts.join("::")
+}
+
+/// Days of the `#[aoc(day<N>, ...)]` and `#[aoc_generator(day<N>)]`
+/// attributes of a source file
+fn attribute_days(content: &str) -> impl Iterator<Item = u32> + '_ {
+    ["#[aoc(", "#[aoc_generator("]
+        .iter()
+        .flat_map(move |attr| {
+            content
+                .match_indices(attr)
+                .map(move |(i, _)| i + attr.len())
+        })
+        .filter_map(move |start| {
+            let args = content[start..].trim_start().strip_prefix("day")?;
+            let digits = args
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(args.len());
+            args[..digits].parse().ok()
+        })
+}
+
+/// Year given to `aoc_lib!` in a source file, if any
+fn lib_year(content: &str) -> Option<u32> {
+    let start = content.find("aoc_lib!")?;
+    let args = &content[start..];
+    let args = args[args.find("year")? + "year".len()..].trim_start();
+    let args = args.strip_prefix('=')?.trim_start();
+    let digits = args
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(args.len());
+    args[..digits].parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn compute(input: String) -> std::vec::IntoIter<String> {
+        vec![input].into_iter()
+    }
+
+    #[test]
+    pub fn aoc_day_input_path() {
+        assert_eq!(
+            AocDay::new(2018, 1).input_path(),
+            PathBuf::from("input/2018/day1.txt")
+        );
+    }
+
+    #[test]
+    pub fn aoc_runner_select() {
+        let runner = AocRunner::new()
+            .with_day(2018, 1, compute)
+            .with_day(2018, 3, compute)
+            .with_day(2017, 5, compute);
+
+        assert_eq!(runner.select(None, None), Some(AocDay::new(2018, 3)));
+        assert_eq!(runner.select(Some(2017), None), Some(AocDay::new(2017, 5)));
+        assert_eq!(runner.select(None, Some(1)), Some(AocDay::new(2018, 1)));
+        assert_eq!(runner.select(Some(2018), Some(5)), None);
+        assert_eq!(AocRunner::new().select(None, None), None);
+    }
+
+    #[test]
+    pub fn aoc_project_sources() {
+        let manifest = "[package]\nname = \"advent-2018\"\n\n[[bin]]\nname = \"aoc-viz\"\n";
+        assert_eq!(
+            read_manifest(manifest),
+            (Some("advent-2018".into()), vec!["aoc-viz".into()])
+        );
+
+        let content = "#[aoc_generator(day3)]\nfn parse() {}\n#[aoc(day12, part2)]\nfn solve() {}";
+        assert_eq!(attribute_days(content).collect::<Vec<_>>(), vec![12, 3]);
+        assert_eq!(lib_year("aoc_lib! { year = 2018 }"), Some(2018));
+        assert_eq!(lib_year("mod day1;"), None);
+
+        assert_eq!(module_path(Path::new("day1.rs")), "day1");
+        assert_eq!(module_path(Path::new("days/day2/mod.rs")), "days::day2");
+        assert_eq!(module_path(Path::new("lib.rs")), "");
+    }
+
+    #[test]
+    pub fn aoc_project_discover() {
+        let root = std::env::temp_dir().join(format!("aoc_viz_project_{}", std::process::id()));
+        fs::create_dir_all(root.join("src/bin")).unwrap();
+        fs::write(root.join("Cargo.toml"), "[package]\nname = \"my-aoc\"\n").unwrap();
+        fs::write(
+            root.join("src/lib.rs"),
+            "mod day1;\naoc_lib! { year = 2017 }",
+        )
+        .unwrap();
+        fs::write(root.join("src/day1.rs"), "#[aoc(day1, part1)]\nfn p1() {}").unwrap();
+        fs::write(root.join("src/bin/other.rs"), "#[aoc(day9, part1)]").unwrap();
+
+        let project = AocProject::discover(&root).unwrap();
+        assert_eq!(project.crate_name, "my_aoc");
+        assert_eq!(project.year, Some(2017));
+        assert_eq!(
+            project.days.into_iter().collect::<Vec<_>>(),
+            vec![(1, "day1".into())]
+        );
+        assert!(!project.has_runner);
+
+        fs::write(root.join("src/bin/aoc-viz.rs"), "").unwrap();
+        let project = AocProject::discover(&root).unwrap();
+        assert!(project.has_runner);
+        assert!(project
+            .runner_example()
+            .contains(".with_day(2017, 1, my_aoc::day1::visualize)"));
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,129 @@
+use cursive::theme::{Color, ColorStyle, ColorType, Effect, PaletteColor};
+use enumset::EnumSet;
+
+/// A value displayed by the FrameView: a glyph, printed with optional
+/// foreground and background colours and text effects.
+///
+/// Colours that are not specified fall back to the current theme's colours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cell {
+    /// Character printed for this cell
+    pub glyph: char,
+    /// Foreground colour, if any
+    pub fg: Option<Color>,
+    /// Background colour, if any
+    pub bg: Option<Color>,
+    /// Effects applied when printing the glyph (bold, underline...)
+    pub effects: EnumSet<Effect>,
+}
+
+impl Cell {
+    /// Creates a new Cell showing the given glyph, without colours nor effects
+    pub fn new(glyph: char) -> Self {
+        Cell {
+            glyph,
+            fg: None,
+            bg: None,
+            effects: EnumSet::new(),
+        }
+    }
+
+    /// Sets the foreground colour of the Cell
+    pub fn fg(mut self, color: Color) -> Self {
+        self.fg = Some(color);
+        self
+    }
+
+    /// Sets the background colour of the Cell
+    pub fn bg(mut self, color: Color) -> Self {
+        self.bg = Some(color);
+        self
+    }
+
+    /// Adds an effect to the Cell
+    pub fn effect(mut self, effect: Effect) -> Self {
+        self.effects.insert(effect);
+        self
+    }
+
+    /// The ColorStyle to use to print this Cell, using the theme's
+    /// primary colours for the unspecified ones
+    pub fn color_style(&self) -> ColorStyle {
+        let front = self
+            .fg
+            .map_or(ColorType::Palette(PaletteColor::Primary), ColorType::Color);
+        let back = self
+            .bg
+            .map_or(ColorType::Palette(PaletteColor::View), ColorType::Color);
+        ColorStyle::new(front, back)
+    }
+}
+
+/// A value that the FrameView knows how to display.
+///
+/// Implement it for your own value types to visualize them directly.
+pub trait Displayable: Clone + Send + 'static {
+    /// Converts the value into the Cell printed on screen
+    fn to_cell(&self) -> Cell;
+}
+
+impl Displayable for Cell {
+    fn to_cell(&self) -> Cell {
+        *self
+    }
+}
+
+impl Displayable for char {
+    fn to_cell(&self) -> Cell {
+        Cell::new(*self)
+    }
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Cell::new(' ')
+    }
+}
+
+impl From<char> for Cell {
+    fn from(glyph: char) -> Self {
+        Cell::new(glyph)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cursive::theme::BaseColor;
+
+    #[test]
+    pub fn cell_builder() {
+        let cell = Cell::new('#')
+            .fg(Color::Dark(BaseColor::Red))
+            .effect(Effect::Bold)
+            .effect(Effect::Underline);
+
+        assert_eq!(cell.glyph, '#');
+        assert_eq!(cell.fg, Some(Color::Dark(BaseColor::Red)));
+        assert_eq!(cell.bg, None);
+        assert!(cell.effects.contains(Effect::Bold));
+        assert!(cell.effects.contains(Effect::Underline));
+        assert!(!cell.effects.contains(Effect::Italic));
+    }
+
+    #[test]
+    pub fn cell_displayable() {
+        assert_eq!('x'.to_cell(), Cell::new('x'));
+        let cell = Cell::new('y').bg(Color::Rgb(1, 2, 3));
+        assert_eq!(cell.to_cell(), cell);
+    }
+
+    #[test]
+    pub fn cell_color_style() {
+        assert_eq!(Cell::from('a').color_style(), ColorStyle::primary());
+
+        let style = Cell::new('a').bg(Color::Rgb(0, 0, 0)).color_style();
+        assert_eq!(style.front, ColorType::Palette(PaletteColor::Primary));
+        assert_eq!(style.back, ColorType::Color(Color::Rgb(0, 0, 0)));
+    }
+}
@@ -0,0 +1,266 @@
+use std::convert::TryFrom;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+/// Coordinates of a value on a 2D plane, that the FrameView knows how to lay out.
+///
+/// Implemented for tuples of integers. Implement it for your own point types
+/// to visualize them directly.
+pub trait Coords: Hash + Eq + Clone + Debug + Send + 'static {
+    /// Builds the coordinates from a position on the plane.
+    /// Returns None if this position can not be represented by this type
+    /// (e.g. negative positions for unsigned coordinates)
+    fn from_xy(x: i64, y: i64) -> Option<Self>;
+
+    /// Horizontal position on the plane
+    fn x(&self) -> i64;
+
+    /// Vertical position on the plane
+    fn y(&self) -> i64;
+}
+
+/// An inclusive bounding box of coordinates on the plane
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Bounds {
+    /// Smallest x and y positions
+    pub min: (i64, i64),
+    /// Biggest x and y positions
+    pub max: (i64, i64),
+}
+
+impl Bounds {
+    /// Creates a Bounds containing a single position
+    pub fn new(x: i64, y: i64) -> Self {
+        Bounds {
+            min: (x, y),
+            max: (x, y),
+        }
+    }
+
+    /// Computes the smallest Bounds containing all the given coordinates.
+    /// Returns None if there are no coordinates.
+    pub fn of<C: Coords>(coords: impl Iterator<Item = C>) -> Option<Self> {
+        coords.fold(None, |bounds: Option<Bounds>, c| match bounds {
+            Some(mut b) => {
+                b.include(c.x(), c.y());
+                Some(b)
+            }
+            None => Some(Bounds::new(c.x(), c.y())),
+        })
+    }
+
+    /// Grows the Bounds so that they contain the given position
+    pub fn include(&mut self, x: i64, y: i64) {
+        self.min = (self.min.0.min(x), self.min.1.min(y));
+        self.max = (self.max.0.max(x), self.max.1.max(y));
+    }
+
+    /// Are the given coordinates inside the Bounds ?
+    pub fn contains<C: Coords>(&self, coords: &C) -> bool {
+        (self.min.0..=self.max.0).contains(&coords.x())
+            && (self.min.1..=self.max.1).contains(&coords.y())
+    }
+
+    /// Position of the given coordinates in a grid covering the Bounds, row
+    /// by row, if they are inside of it. Coordinates saturating to i64::MAX
+    /// are left out, since several of them share that position (cf. `Coords::x`)
+    pub fn grid_position<C: Coords>(&self, coords: &C) -> Option<usize> {
+        let (x, y) = (coords.x(), coords.y());
+        if x == i64::MAX || y == i64::MAX || !self.contains(coords) {
+            return None;
+        }
+        // No grid covers Bounds with more positions than a usize can count
+        usize::try_from(self.area()?).ok()?;
+        let x = (i128::from(x) - i128::from(self.min.0)) as usize;
+        let y = (i128::from(y) - i128::from(self.min.1)) as usize;
+        Some(y * self.width() as usize + x)
+    }
+
+    /// Coordinates at the given position of a grid covering the Bounds,
+    /// row by row (cf. `grid_position`), if they can be represented
+    pub fn grid_coords<C: Coords>(&self, position: usize) -> Option<C> {
+        let width = usize::try_from(self.width()).ok()?;
+        C::from_xy(
+            self.min
+                .0
+                .checked_add(i64::try_from(position % width).ok()?)?,
+            self.min
+                .1
+                .checked_add(i64::try_from(position / width).ok()?)?,
+        )
+    }
+
+    /// Center of the Bounds, rounded down
+    pub fn center(&self) -> (i64, i64) {
+        let middle = |min: i64, max: i64| (i128::from(min) + i128::from(max)).div_euclid(2) as i64;
+        (
+            middle(self.min.0, self.max.0),
+            middle(self.min.1, self.max.1),
+        )
+    }
+
+    /// Number of columns contained in the Bounds, saturating to u64::MAX
+    pub fn width(&self) -> u64 {
+        u64::try_from(length(self.min.0, self.max.0)).unwrap_or(u64::MAX)
+    }
+
+    /// Number of rows contained in the Bounds, saturating to u64::MAX
+    pub fn height(&self) -> u64 {
+        u64::try_from(length(self.min.1, self.max.1)).unwrap_or(u64::MAX)
+    }
+
+    /// Number of positions contained in the Bounds, if it fits in a u64
+    pub fn area(&self) -> Option<u64> {
+        let area = length(self.min.0, self.max.0).checked_mul(length(self.min.1, self.max.1))?;
+        u64::try_from(area).ok()
+    }
+}
+
+/// Number of positions from min to max, both included
+fn length(min: i64, max: i64) -> u128 {
+    (i128::from(max) - i128::from(min) + 1) as u128
+}
+
+/// Bounds and centroid of a set of positions on the plane, that can be grown
+/// one position at a time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Extent {
+    /// Bounds of the positions
+    pub bounds: Bounds,
+    /// Sum of the positions, used to compute the centroid
+    sum: (i128, i128),
+    /// Number of positions
+    count: u64,
+}
+
+impl Extent {
+    /// Creates an Extent containing a single position
+    pub fn new(x: i64, y: i64) -> Self {
+        Extent {
+            bounds: Bounds::new(x, y),
+            sum: (i128::from(x), i128::from(y)),
+            count: 1,
+        }
+    }
+
+    /// Adds the given position to the Extent
+    pub fn include(&mut self, x: i64, y: i64) {
+        self.bounds.include(x, y);
+        self.sum = (self.sum.0 + i128::from(x), self.sum.1 + i128::from(y));
+        self.count += 1;
+    }
+
+    /// Average position, rounded towards zero
+    pub fn centroid(&self) -> (i64, i64) {
+        let count = i128::from(self.count);
+        ((self.sum.0 / count) as i64, (self.sum.1 / count) as i64)
+    }
+}
+
+macro_rules! impl_coords_for_tuple {
+    ($($t:ty),*) => {
+        $(
+            impl Coords for ($t, $t) {
+                fn from_xy(x: i64, y: i64) -> Option<Self> {
+                    Some((<$t>::try_from(x).ok()?, <$t>::try_from(y).ok()?))
+                }
+
+                fn x(&self) -> i64 {
+                    i64::try_from(self.0).unwrap_or(i64::MAX)
+                }
+
+                fn y(&self) -> i64 {
+                    i64::try_from(self.1).unwrap_or(i64::MAX)
+                }
+            }
+        )*
+    };
+}
+
+// Positions beyond i64::MAX, only reachable by u64 and usize, saturate to it
+impl_coords_for_tuple!(i16, i32, i64, isize, u16, u32, u64, usize);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn coords_from_xy() {
+        assert_eq!(<(i32, i32)>::from_xy(-3, 4), Some((-3, 4)));
+        assert_eq!(<(i64, i64)>::from_xy(i64::MIN, 0), Some((i64::MIN, 0)));
+        assert_eq!(<(usize, usize)>::from_xy(3, 4), Some((3, 4)));
+
+        // Unrepresentable positions
+        assert_eq!(<(usize, usize)>::from_xy(-1, 4), None);
+        assert_eq!(<(i32, i32)>::from_xy(i64::MAX, 0), None);
+    }
+
+    #[test]
+    pub fn coords_xy_saturate() {
+        assert_eq!((u64::MAX, 3u64).x(), i64::MAX);
+        assert_eq!((3usize, usize::MAX).y(), i64::MAX);
+        assert_eq!((-3i32, 4i32).x(), -3);
+    }
+
+    #[test]
+    pub fn coords_bounds() {
+        let coords: Vec<(i32, i32)> = vec![(1, 2), (-3, 5), (4, -1)];
+        let bounds = Bounds::of(coords.iter().cloned()).unwrap();
+        assert_eq!(bounds.min, (-3, -1));
+        assert_eq!(bounds.max, (4, 5));
+        assert_eq!(bounds.width(), 8);
+        assert_eq!(bounds.height(), 7);
+        assert_eq!(bounds.area(), Some(56));
+
+        assert_eq!(bounds.center(), (0, 2));
+        assert!(bounds.contains(&(-3, 5)));
+        assert!(bounds.contains(&(0, 0)));
+        assert!(!bounds.contains(&(5, 0)));
+        assert!(!bounds.contains(&(0, -2)));
+
+        let empty: Vec<(i32, i32)> = vec![];
+        assert_eq!(Bounds::of(empty.into_iter()), None);
+
+        assert_eq!(bounds.grid_position(&(-3, -1)), Some(0));
+        assert_eq!(bounds.grid_position(&(0, 2)), Some(27));
+        assert_eq!(bounds.grid_coords(27), Some((0, 2)));
+        assert_eq!(bounds.grid_position(&(5, 0)), None);
+        let mut bounds = Bounds::new(0, 0);
+        bounds.include(i64::MAX, 0);
+        assert_eq!(bounds.grid_position(&(u64::MAX, 0u64)), None);
+    }
+
+    #[test]
+    pub fn coords_bounds_overflow() {
+        let mut bounds = Bounds::new(i64::MIN, 0);
+        bounds.include(i64::MAX, 0);
+        assert_eq!((bounds.width(), bounds.height()), (u64::MAX, 1));
+        assert_eq!(bounds.area(), None);
+        assert_eq!(bounds.center(), (-1, 0));
+        assert_eq!(bounds.grid_position(&(i64::MAX - 1, 0i64)), None);
+
+        bounds.include(0, i64::MAX);
+        assert_eq!(bounds.area(), None);
+        assert_eq!(bounds.center(), (-1, i64::MAX / 2));
+    }
+
+    #[test]
+    pub fn coords_extent() {
+        let mut extent = Extent::new(1, 2);
+        extent.include(-3, 6);
+        extent.include(5, 1);
+        assert_eq!(extent.bounds.min, (-3, 1));
+        assert_eq!(extent.bounds.max, (5, 6));
+        assert_eq!(extent.centroid(), (1, 3));
+    }
+
+    #[test]
+    pub fn coords_xy() {
+        let coords: (usize, usize) = (3, 4);
+        assert_eq!((coords.x(), coords.y()), (3, 4));
+        let coords: (i16, i16) = (-3, -4);
+        assert_eq!((coords.x(), coords.y()), (-3, -4));
+    }
+}
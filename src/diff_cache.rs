@@ -1,42 +1,528 @@
+use crate::coords::{Bounds, Coords, Extent};
+use crate::error::StorageError;
 use crate::state_tree::StateTree;
+use crate::storage::{hashed_memory_usage, AnyStorage, Storage};
 use core::fmt::Debug;
-use std::collections::HashMap;
-use std::hash::Hash;
+use std::collections::{BTreeMap, HashMap};
+use std::mem::size_of;
+use std::ops::Bound::{Excluded, Included, Unbounded};
+use std::ops::Range;
+
+/// Default number of indices with changes between two keyframes
+const KEYFRAME_INTERVAL: usize = 64;
+
+/// Share of its memory budget a DiffCache gets back to once over budget,
+/// so that the history is not reduced again at every index
+const BUDGET_TARGET: f64 = 0.75;
+
+/// Growth of its memory usage after which a DiffCache that could not get back
+/// under its budget tries to reduce its history again, so that a history that
+/// cannot be reduced any further is not scanned at every index
+const BUDGET_RETRY_GROWTH: f64 = 1.25;
+
+/// Number of states stored up to the index of a keyframe, for each
+/// coordinates that got a value by then.
+/// The counts are laid out as the grid of the storage if it has one, so that
+/// reading them does not hash the coordinates of the grid
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Keyframe<C: Coords> {
+    /// Rectangle covered by the grid, if any
+    grid: Option<Bounds>,
+    /// Counts of the coordinates of the grid, row by row
+    cells: Vec<usize>,
+    /// Counts of the other coordinates
+    counts: HashMap<C, usize>,
+}
+
+impl<C: Coords> Keyframe<C> {
+    /// Creates an empty Keyframe, laid out as the given grid if any
+    fn new(grid: Option<Bounds>) -> Self {
+        let area = grid.and_then(|g| g.area()).map_or(0, |area| area as usize);
+        Keyframe {
+            grid,
+            cells: vec![0; area],
+            counts: HashMap::new(),
+        }
+    }
+
+    /// Position of the given coordinates in the grid, if they are inside of it
+    fn position(&self, coords: &C) -> Option<usize> {
+        self.grid.as_ref()?.grid_position(coords)
+    }
+
+    /// Number of states stored at the given coordinates up to this keyframe
+    fn count(&self, coords: &C) -> usize {
+        match self.position(coords) {
+            Some(p) => self.cells[p],
+            None => self.counts.get(coords).copied().unwrap_or(0),
+        }
+    }
+
+    /// Counts more states at the given coordinates
+    fn add(&mut self, coords: C, states: usize) {
+        match self.position(&coords) {
+            Some(p) => self.cells[p] += states,
+            None => *self.counts.entry(coords).or_insert(0) += states,
+        }
+    }
+
+    /// Estimated number of bytes used by the counts
+    fn memory_usage(&self) -> usize {
+        self.cells.capacity() * size_of::<usize>() + hashed_memory_usage(&self.counts)
+    }
+}
+
+/// Memory a DiffCache may use, and how to tell that two values are the same
+#[derive(Clone)]
+struct Budget<V> {
+    bytes: usize,
+    same: fn(&V, &V) -> bool,
+    /// Memory usage above which the history is reduced again, after a
+    /// reduction that did not get back under budget. 0 otherwise
+    retry_above: usize,
+}
 
 /// A generic struct that provides a way to keep track of changes
 /// applied on a value V, present at coordinates C, over a given index K.
-pub struct DiffCache<C: Hash + Eq, K: Ord + Eq + Copy + Debug, V> {
+///
+/// Every few indices, a keyframe records how many states of each coordinates
+/// are stored up to that index. The value of coordinates in a frame is then
+/// found by scanning the few states stored since the nearest keyframe,
+/// instead of searching their whole history (cf. `frame`).
+///
+/// The StateTree of each coordinates is kept in a Storage S, picking between
+/// a sparse and a dense one by default (cf. `AnyStorage`).
+///
+/// A memory budget can be given, in which case the oldest part of the history
+/// gets decimated once over budget (cf. `with_memory_budget`).
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DiffCache<C: Coords, K: Ord + Eq + Copy + Debug, V, S = AnyStorage<C, K, V>> {
     /// Underlying data of the DiffCache
-    data: HashMap<C, StateTree<K, V>>,
+    data: S,
     /// Default representation of a Value
     default: V,
+    /// Bounds of all the coordinates that got a value
+    bounds: Option<Bounds>,
+    /// Extent of the coordinates that got a value, at each index
+    extents: BTreeMap<K, Extent>,
+    /// Coordinates that got a new value, at each index
+    changes: BTreeMap<K, Vec<C>>,
+    /// Full frames at some of the indices
+    keyframes: BTreeMap<K, Keyframe<C>>,
+    /// Number of indices with changes between two keyframes
+    keyframe_interval: usize,
+    /// Number of states stored
+    states: usize,
+    /// Memory the DiffCache may use, if limited. Not saved with the states
+    #[cfg_attr(feature = "serde", serde(skip, default = "Option::default"))]
+    budget: Option<Budget<V>>,
+    /// Ranges of indices whose frames are approximated since their history
+    /// was decimated, as start => end (excluded)
+    decimated: BTreeMap<K, K>,
 }
 
-impl<C: Hash + Eq, K: Ord + Eq + Copy + Debug, V> DiffCache<C, K, V> {
+impl<C, K, V, S> DiffCache<C, K, V, S>
+where
+    C: Coords,
+    K: Ord + Eq + Copy + Debug,
+    S: Storage<C, K, V>,
+{
     /// Creates a new instance of a DiffCache with the given generics
-    pub fn new(default: V) -> Self {
+    pub fn new(default: V) -> Self
+    where
+        S: Default,
+    {
+        Self::with_storage(default, S::default())
+    }
+
+    /// Creates a new instance of a DiffCache keeping its data in the given storage
+    pub fn with_storage(default: V, storage: S) -> Self {
         DiffCache {
-            data: HashMap::default(),
+            data: storage,
             default,
+            bounds: None,
+            extents: BTreeMap::new(),
+            changes: BTreeMap::new(),
+            keyframes: BTreeMap::new(),
+            keyframe_interval: KEYFRAME_INTERVAL,
+            states: 0,
+            budget: None,
+            decimated: BTreeMap::new(),
+        }
+    }
+
+    /// Stores a keyframe every given number of indices with changes.
+    /// Shorter intervals make rebuilding a frame faster, at the cost of memory
+    pub fn with_keyframe_interval(mut self, interval: usize) -> Self {
+        self.keyframe_interval = interval.max(1);
+        self
+    }
+
+    /// Keeps the memory used under about the given number of bytes (cf. `memory_usage`).
+    ///
+    /// Once over budget, consecutive states holding the same value are merged.
+    /// If that is not enough, every other index with changes in the oldest half
+    /// of the history is dropped, its states being moved to the next index with
+    /// changes, until the budget is met or too few indices are left.
+    /// The frames of the dropped indices are then approximated (cf. `decimated`).
+    /// If the history cannot get back under budget, it is only reduced again
+    /// once its memory usage grew by a quarter.
+    ///
+    /// A DiffCache already over budget, e.g. loaded from a snapshot, is reduced
+    /// right away
+    pub fn with_memory_budget(mut self, bytes: usize) -> Self
+    where
+        V: PartialEq,
+    {
+        self.budget = Some(Budget {
+            bytes,
+            same: V::eq,
+            retry_above: 0,
+        });
+        self.enforce_budget();
+        self
+    }
+
+    /// Estimated number of bytes used by the storage, the states, the
+    /// coordinates changed and the extent at each index, and the keyframes.
+    ///
+    /// This is a lower bound: the memory owned by the values (e.g. a String),
+    /// the spare capacity of the Vecs of states and changes, and the nodes of
+    /// the BTreeMaps are not counted
+    pub fn memory_usage(&self) -> usize {
+        let states = self.data.memory_usage() + self.states * size_of::<(K, V)>();
+        let changes = self.states * size_of::<C>()
+            + self.changes.len() * (size_of::<K>() + size_of::<Vec<C>>());
+        let extents = self.extents.len() * (size_of::<K>() + size_of::<Extent>());
+        let keyframes: usize = self
+            .keyframes
+            .values()
+            .map(|keyframe| size_of::<(K, Keyframe<C>)>() + keyframe.memory_usage())
+            .sum();
+        states + changes + extents + keyframes
+    }
+
+    /// Ranges of indices whose frames are approximated, because their history
+    /// was decimated to stay under the memory budget, by increasing index
+    pub fn decimated(&self) -> impl Iterator<Item = Range<K>> + '_ {
+        self.decimated.iter().map(|(start, end)| *start..*end)
+    }
+
+    /// Completes the frame at the last index with changes, when states start
+    /// being stored at a later index
+    fn prepare(&mut self, index: K) {
+        if self
+            .changes
+            .keys()
+            .next_back()
+            .is_some_and(|last| index > *last)
+        {
+            self.checkpoint();
         }
     }
 
     /// Adds a V value information for a given C coordinate and a given K index
-    /// Panics if we failed to insert a StateTree in the inner data
-    /// TODO: Should not panic, maybe return a boolean ?
-    pub fn push(&mut self, coords: C, index: K, value: V) {
-        let mut entry = self.data.get_mut(&coords);
-        match entry {
-            Some(ref mut tree) => tree.push(index, value),
+    /// Fails if there is already a value for these coordinates at this index
+    pub fn push(&mut self, coords: C, index: K, value: V) -> Result<(), StorageError<K>> {
+        self.prepare(index);
+        match self.data.get_mut(&coords) {
+            Some(tree) => tree.push(index, value)?,
+            None => {
+                self.data
+                    .insert(coords.clone(), StateTree::new(index, value));
+            }
+        }
+        self.track(coords, index);
+        Ok(())
+    }
+
+    /// Adds a V value information for a given C coordinate and a given K index,
+    /// replacing the value that was already there if any.
+    /// Returns the replaced value.
+    pub fn overwrite(&mut self, coords: C, index: K, value: V) -> Option<V> {
+        self.prepare(index);
+        let replaced = match self.data.get_mut(&coords) {
+            Some(tree) => tree.overwrite(index, value),
             None => {
-                self.data.insert(coords, StateTree::new(index, value));
+                self.data
+                    .insert(coords.clone(), StateTree::new(index, value));
+                None
             }
         };
+        if replaced.is_none() {
+            self.track(coords, index);
+        }
+        replaced
+    }
+
+    /// Records a new state at the given coordinates and index: grows the bounds
+    /// and the extent at this index, and keeps the keyframes up to date
+    fn track(&mut self, coords: C, index: K) {
+        // A state stored before a keyframe changes the counts it records
+        if let Some(&last) = self.keyframes.keys().next_back() {
+            if index <= last {
+                self.keyframes.split_off(&index);
+            }
+        }
+
+        let (x, y) = (coords.x(), coords.y());
+        self.states += 1;
+        self.changes.entry(index).or_default().push(coords);
+        match self.bounds {
+            Some(ref mut bounds) => bounds.include(x, y),
+            None => self.bounds = Some(Bounds::new(x, y)),
+        }
+        self.extents
+            .entry(index)
+            .and_modify(|e| e.include(x, y))
+            .or_insert_with(|| Extent::new(x, y));
+    }
+
+    /// Stores a keyframe at the last index with changes, once there are enough
+    /// of them since the previous keyframe, and keeps to the memory budget.
+    /// Called before storing a state at a later index, which means that the
+    /// frame at the last index is complete.
+    fn checkpoint(&mut self) {
+        let last = match self.changes.keys().next_back() {
+            Some(&last) => last,
+            None => return,
+        };
+        if let Some(ref bounds) = self.bounds {
+            self.data.reshape(bounds);
+        }
+        self.enforce_budget();
+        let from = match self.keyframes.keys().next_back() {
+            Some(&keyframe) => Excluded(keyframe),
+            None => Unbounded,
+        };
+        let since = self.changes.range((from, Included(last))).count();
+        if since >= self.keyframe_interval {
+            // No state is stored after the last index yet
+            let mut keyframe = Keyframe::new(self.data.grid());
+            self.data
+                .iter()
+                .for_each(|(c, tree)| keyframe.add(c, tree.len()));
+            self.keyframes.insert(last, keyframe);
+        }
+    }
+
+    /// Reduces the history until the memory used gets back under the budget,
+    /// if over it and not waiting for it to grow after a failed reduction
+    fn enforce_budget(&mut self) {
+        let usage = self.memory_usage();
+        let (bytes, same) = match self.budget {
+            Some(ref budget) if usage > budget.bytes.max(budget.retry_above) => {
+                (budget.bytes, budget.same)
+            }
+            _ => return,
+        };
+        let removed: usize = self
+            .data
+            .iter_mut()
+            .map(|(_, tree)| tree.compact(same))
+            .sum();
+        if removed > 0 {
+            self.reindex();
+        }
+        let target = (bytes as f64 * BUDGET_TARGET) as usize;
+        while self.memory_usage() > target && self.decimate() {}
+        let usage = self.memory_usage();
+        if let Some(ref mut budget) = self.budget {
+            budget.retry_above = if usage > bytes {
+                (usage as f64 * BUDGET_RETRY_GROWTH) as usize
+            } else {
+                0
+            };
+        }
+    }
+
+    /// Drops every other index with changes in the oldest half of the history,
+    /// moving its states to the next index with changes.
+    /// Returns false if there are too few indices to do so
+    fn decimate(&mut self) -> bool {
+        let indices: Vec<K> = self.changes.keys().copied().collect();
+        let oldest = indices.len() / 2;
+        if oldest < 2 {
+            return false;
+        }
+        let later: BTreeMap<K, K> = (0..oldest)
+            .step_by(2)
+            .map(|i| (indices[i], indices[i + 1]))
+            .collect();
+        self.data.iter_mut().for_each(|(_, tree)| {
+            tree.merge_forward(|index| later.get(index).copied());
+        });
+        let end = *later.values().next_back().unwrap();
+        self.mark_decimated(indices[0], end);
+        self.reindex();
+        true
+    }
+
+    /// Records that the frames from start to end (excluded) are approximated,
+    /// merging the range with the ones it touches
+    fn mark_decimated(&mut self, mut start: K, mut end: K) {
+        let touched: Vec<(K, K)> = self
+            .decimated
+            .range(..=end)
+            .filter(|(_, e)| **e >= start)
+            .map(|(s, e)| (*s, *e))
+            .collect();
+        touched.into_iter().for_each(|(s, e)| {
+            self.decimated.remove(&s);
+            start = start.min(s);
+            end = end.max(e);
+        });
+        self.decimated.insert(start, end);
+    }
+
+    /// Rebuilds the changes, extents and keyframes from the stored states,
+    /// once some of them were merged or moved
+    fn reindex(&mut self) {
+        let mut changes: BTreeMap<K, Vec<C>> = BTreeMap::new();
+        self.data.iter().for_each(|(c, tree)| {
+            tree.iter()
+                .for_each(|(index, _)| changes.entry(*index).or_default().push(c.clone()))
+        });
+        self.states = changes.values().map(Vec::len).sum();
+        self.extents = changes
+            .iter()
+            .map(|(index, coords)| {
+                let mut extent = Extent::new(coords[0].x(), coords[0].y());
+                coords
+                    .iter()
+                    .skip(1)
+                    .for_each(|c| extent.include(c.x(), c.y()));
+                (*index, extent)
+            })
+            .collect();
+
+        self.keyframes = BTreeMap::new();
+        let mut keyframe = Keyframe::new(self.data.grid());
+        for (count, (index, coords)) in changes.iter().enumerate() {
+            coords.iter().for_each(|c| keyframe.add(c.clone(), 1));
+            if (count + 1) % self.keyframe_interval == 0 {
+                self.keyframes.insert(*index, keyframe.clone());
+            }
+        }
+        self.changes = changes;
+    }
+
+    /// Bounds of all the coordinates that got a value, if any
+    pub fn bounds(&self) -> Option<Bounds> {
+        self.bounds
+    }
+
+    /// Bounds and centroid of the coordinates that got a value exactly at
+    /// the given index, if any
+    pub fn extent_at(&self, index: K) -> Option<&Extent> {
+        self.extents.get(&index)
     }
 
     /// Adds all the information contained in the input `Iterator`
-    pub fn append(&mut self, input: impl Iterator<Item = (C, K, V)>) {
-        input.for_each(|i| self.push(i.0, i.1, i.2))
+    /// Stops at the first information that could not be stored
+    pub fn append(
+        &mut self,
+        input: impl Iterator<Item = (C, K, V)>,
+    ) -> Result<(), StorageError<K>> {
+        input.into_iter().try_for_each(|i| self.push(i.0, i.1, i.2))
+    }
+
+    /// Adds all the information contained in the input `Iterator`,
+    /// overwriting the values that were already stored
+    pub fn append_overwrite(&mut self, input: impl Iterator<Item = (C, K, V)>) {
+        input.for_each(|i| {
+            self.overwrite(i.0, i.1, i.2);
+        })
+    }
+
+    /// All the coordinates that got a value at some index, in no particular order
+    pub fn coords(&self) -> impl Iterator<Item = C> + '_ {
+        self.data.iter().map(|(c, _)| c)
+    }
+
+    /// Number of coordinates that got a value at some index
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Whether no coordinates got a value yet
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// All the coordinates that got a new value exactly at the given index,
+    /// in no particular order
+    pub fn changed_at(&self, index: K) -> impl Iterator<Item = &C> {
+        self.changes.get(&index).into_iter().flatten()
+    }
+
+    /// Every change of the value at the given coordinates, as (index, value),
+    /// by increasing index. Empty if these coordinates never got a value.
+    pub fn history(&self, coords: &C) -> impl Iterator<Item = (&K, &V)> {
+        self.data
+            .get(coords)
+            .into_iter()
+            .flat_map(|tree| tree.iter())
+    }
+
+    /// Smallest index strictly greater than the given one at which the value
+    /// at the given coordinates changes
+    pub fn next_change(&self, coords: &C, index: K) -> Option<K> {
+        self.data
+            .get(coords)
+            .and_then(|tree| tree.next_change(&index))
+            .cloned()
+    }
+
+    /// Biggest index strictly smaller than the given one at which the value
+    /// at the given coordinates changes
+    pub fn previous_change(&self, coords: &C, index: K) -> Option<K> {
+        self.data
+            .get(coords)
+            .and_then(|tree| tree.previous_change(&index))
+            .cloned()
+    }
+
+    /// Smallest index strictly greater than the given one at which the value
+    /// at any of the given coordinates changes
+    pub fn next_change_in<'a>(&self, coords: impl Iterator<Item = &'a C>, index: K) -> Option<K>
+    where
+        C: 'a,
+    {
+        coords.filter_map(|c| self.next_change(c, index)).min()
+    }
+
+    /// Biggest index strictly smaller than the given one at which the value
+    /// at any of the given coordinates changes
+    pub fn previous_change_in<'a>(&self, coords: impl Iterator<Item = &'a C>, index: K) -> Option<K>
+    where
+        C: 'a,
+    {
+        coords.filter_map(|c| self.previous_change(c, index)).max()
+    }
+
+    /// Searches across time for the first index, starting from the given one,
+    /// at which a value matching the predicate is stored at some coordinates.
+    /// Only the states stored up to that index are read (cf. `changes_from`)
+    pub fn find_change(&self, from: K, predicate: impl Fn(&C, &V) -> bool) -> Option<K> {
+        self.changes_from(from)
+            .find(|(_, c, v)| predicate(c, v))
+            .map(|(index, _, _)| index)
+    }
+
+    /// Every state stored at or after the given index, as (index, coordinates,
+    /// value), by increasing index
+    pub fn changes_from(&self, from: K) -> impl Iterator<Item = (K, &C, &V)> {
+        self.changes
+            .range(from..)
+            .flat_map(move |(&index, coords)| {
+                coords.iter().filter_map(move |c| {
+                    let value = self.data.get(c)?.search(index)?;
+                    Some((index, c, value))
+                })
+            })
     }
 
     /// Searches for a value V at given coordinates for a given index
@@ -48,11 +534,79 @@ impl<C: Hash + Eq, K: Ord + Eq + Copy + Debug, V> DiffCache<C, K, V> {
         }
     }
 
+    /// Searches for a value V at given coordinates for a given index
+    /// If no data was found, then default is returned.
+    pub fn search_or_default(&self, coords: C, index: K) -> &V {
+        self.search(coords, index).unwrap_or(&self.default)
+    }
+
+    /// Value of the coordinates that were never set
+    pub fn default_value(&self) -> &V {
+        &self.default
+    }
+
+    /// The full frame at the given index, whose values are found from the
+    /// nearest keyframe. Nothing is copied nor computed until they are read
+    pub fn frame(&self, index: K) -> Frame<'_, C, K, V, S> {
+        Frame {
+            cache: self,
+            index,
+            keyframe: self.keyframes.range(..=index).next_back().map(|(_, k)| k),
+        }
+    }
+
     /// Takes a view into the DiffCache, maps all the given coordinates
     /// to their representation at index K.
     /// If no data was found, then default is returned.
     pub fn view(&self, coords: impl Iterator<Item = C>, index: K) -> impl Iterator<Item = &V> {
-        coords.map(move |c| self.search(c, index).unwrap_or(&self.default))
+        let frame = self.frame(index);
+        coords.map(move |c| frame.get(&c))
+    }
+}
+
+/// Every value stored in a DiffCache at a given index (cf. `DiffCache::frame`)
+pub struct Frame<'a, C: Coords, K: Ord + Eq + Copy + Debug, V, S = AnyStorage<C, K, V>> {
+    cache: &'a DiffCache<C, K, V, S>,
+    index: K,
+    /// Nearest keyframe at or before this index, if any
+    keyframe: Option<&'a Keyframe<C>>,
+}
+
+impl<'a, C, K, V, S> Frame<'a, C, K, V, S>
+where
+    C: Coords,
+    K: Ord + Eq + Copy + Debug,
+    S: Storage<C, K, V>,
+{
+    /// Value at the given coordinates in this frame.
+    /// If no data was found, then default is returned.
+    pub fn get(&self, coords: &C) -> &'a V {
+        self.cache
+            .data
+            .get(coords)
+            .and_then(|tree| self.search(coords, tree))
+            .unwrap_or(&self.cache.default)
+    }
+
+    /// Value of the given StateTree at this index, scanning the states
+    /// stored since the keyframe
+    fn search(&self, coords: &C, tree: &'a StateTree<K, V>) -> Option<&'a V> {
+        let known = self.keyframe.map_or(0, |k| k.count(coords));
+        tree.search_from(known, &self.index)
+    }
+
+    /// Value of the coordinates that were never set
+    pub fn default_value(&self) -> &'a V {
+        &self.cache.default
+    }
+
+    /// Every coordinates that got a value by this frame, with their value
+    /// in it, in no particular order
+    pub fn iter(&self) -> impl Iterator<Item = (C, &'a V)> + '_ {
+        self.cache.data.iter().filter_map(move |(c, tree)| {
+            let value = self.search(&c, tree)?;
+            Some((c, value))
+        })
     }
 }
 
@@ -70,14 +624,14 @@ mod tests {
     /// Inserting in a DiffCache should not panic
     pub fn diffcache_push() {
         let mut cache: DiffCache<(i32, i32), usize, char> = DiffCache::new('.');
-        cache.push((0, 0), 0, 'a');
-        cache.push((0, 0), 5, 'b');
-        cache.push((1, 0), 0, 'a');
-        cache.push((1, 0), 3, 'b');
-        cache.push((0, 1), 0, 'a');
-        cache.push((0, 1), 2, 'b');
-        cache.push((1, 1), 0, 'a');
-        cache.push((1, 1), 1, 'b');
+        cache.push((0, 0), 0, 'a').unwrap();
+        cache.push((0, 0), 5, 'b').unwrap();
+        cache.push((1, 0), 0, 'a').unwrap();
+        cache.push((1, 0), 3, 'b').unwrap();
+        cache.push((0, 1), 0, 'a').unwrap();
+        cache.push((0, 1), 2, 'b').unwrap();
+        cache.push((1, 1), 0, 'a').unwrap();
+        cache.push((1, 1), 1, 'b').unwrap();
     }
 
     #[test]
@@ -94,7 +648,7 @@ mod tests {
             ((1, 1), 0, 'a'),
             ((1, 1), 1, 'b'),
         ];
-        cache.append(infos.into_iter());
+        cache.append(infos.into_iter()).unwrap();
     }
 
     #[test]
@@ -110,12 +664,108 @@ mod tests {
             ((1, 1), 0, 'a'),
             ((1, 1), 1, 'b'),
         ];
-        cache.append(infos.into_iter());
+        cache.append(infos.into_iter()).unwrap();
 
         assert_eq!(cache.search((0, 0), 1), Some(&'a'));
         assert_eq!(cache.search((0, 0), 5), Some(&'b'));
     }
 
+    #[test]
+    pub fn diffcache_changed_at() {
+        let mut cache: DiffCache<(i32, i32), usize, char> = DiffCache::new('.');
+        let infos = vec![
+            ((0, 0), 0, 'a'),
+            ((0, 0), 5, 'b'),
+            ((1, 0), 0, 'a'),
+            ((1, 0), 5, 'b'),
+            ((0, 1), 2, 'b'),
+        ];
+        cache.append(infos.into_iter()).unwrap();
+
+        let mut changed: Vec<&(i32, i32)> = cache.changed_at(5).collect();
+        changed.sort();
+        assert_eq!(changed, vec![&(0, 0), &(1, 0)]);
+        assert_eq!(cache.changed_at(2).collect::<Vec<_>>(), vec![&(0, 1)]);
+        assert_eq!(cache.changed_at(3).count(), 0);
+    }
+
+    #[test]
+    pub fn diffcache_history() {
+        let mut cache: DiffCache<(i32, i32), usize, char> = DiffCache::new('.');
+        let infos = vec![((0, 0), 5, 'b'), ((0, 0), 0, 'a'), ((1, 0), 3, 'c')];
+        cache.append(infos.into_iter()).unwrap();
+
+        let history: Vec<(&usize, &char)> = cache.history(&(0, 0)).collect();
+        assert_eq!(history, vec![(&0, &'a'), (&5, &'b')]);
+        assert_eq!(cache.history(&(2, 2)).count(), 0);
+    }
+
+    #[test]
+    pub fn diffcache_changes() {
+        let mut cache: DiffCache<(i32, i32), usize, char> = DiffCache::new('.');
+        let infos = vec![
+            ((0, 0), 1, 'a'),
+            ((0, 0), 8, 'b'),
+            ((1, 0), 4, 'a'),
+            ((2, 0), 6, 'a'),
+        ];
+        cache.append(infos.into_iter()).unwrap();
+
+        assert_eq!(cache.next_change(&(0, 0), 1), Some(8));
+        assert_eq!(cache.previous_change(&(0, 0), 8), Some(1));
+        assert_eq!(cache.next_change(&(3, 0), 0), None);
+
+        let viewport = [(0, 0), (1, 0), (3, 0)];
+        assert_eq!(cache.next_change_in(viewport.iter(), 1), Some(4));
+        assert_eq!(cache.next_change_in(viewport.iter(), 4), Some(8));
+        assert_eq!(cache.next_change_in(viewport.iter(), 8), None);
+        assert_eq!(cache.previous_change_in(viewport.iter(), 8), Some(4));
+        assert_eq!(cache.previous_change_in(viewport.iter(), 1), None);
+    }
+
+    #[test]
+    pub fn diffcache_find_change() {
+        let mut cache: DiffCache<(i32, i32), usize, char> = DiffCache::new('.');
+        let infos = vec![
+            ((0, 0), 1, '#'),
+            ((0, 0), 8, '@'),
+            ((1, 0), 4, '@'),
+            ((1, 0), 6, '#'),
+        ];
+        cache.append(infos.into_iter()).unwrap();
+
+        assert_eq!(cache.find_change(0, |_, v| *v == '@'), Some(4));
+        assert_eq!(cache.find_change(5, |_, v| *v == '@'), Some(8));
+        assert_eq!(cache.find_change(2, |c, v| *c == (0, 0) && *v == '#'), None);
+        assert_eq!(cache.find_change(0, |_, v| *v == 'x'), None);
+
+        let changes: Vec<(usize, &(i32, i32), &char)> = cache.changes_from(5).collect();
+        assert_eq!(changes, vec![(6, &(1, 0), &'#'), (8, &(0, 0), &'@')]);
+    }
+
+    #[test]
+    pub fn diffcache_bounds() {
+        let mut cache: DiffCache<(i32, i32), usize, char> = DiffCache::new('.');
+        assert_eq!(cache.bounds(), None);
+
+        let infos = vec![((-2, 0), 1, 'a'), ((4, 3), 1, 'b'), ((1, -5), 2, 'c')];
+        cache.append(infos.into_iter()).unwrap();
+        cache.overwrite((4, 3), 1, 'd');
+
+        let bounds = cache.bounds().unwrap();
+        assert_eq!((bounds.min, bounds.max), ((-2, -5), (4, 3)));
+        let extent = cache.extent_at(1).unwrap();
+        assert_eq!((extent.bounds.min, extent.bounds.max), ((-2, 0), (4, 3)));
+        assert_eq!(extent.centroid(), (1, 1));
+        assert_eq!(cache.extent_at(2).unwrap().centroid(), (1, -5));
+        assert!(cache.extent_at(3).is_none());
+
+        // Failed pushes are not tracked
+        assert!(cache.push((100, 100), 2, 'e').is_ok());
+        assert!(cache.push((100, 100), 2, 'f').is_err());
+        assert_eq!(cache.extent_at(2).unwrap().centroid(), (50, 47));
+    }
+
     #[test]
     pub fn diffcache_view() {
         let mut cache: DiffCache<(i32, i32), usize, char> = DiffCache::new('.');
@@ -129,11 +779,165 @@ mod tests {
             ((1, 1), 0, 'a'),
             ((1, 1), 1, 'b'),
         ];
-        cache.append(infos.into_iter());
+        cache.append(infos.into_iter()).unwrap();
         let expected: Vec<&char> = vec![&'a', &'b', &'b', &'b'];
         let actual: Vec<&char> = cache
             .view((0..2).flat_map(|y| (0..2).map(move |x| (x, y))), 4)
             .collect();
         assert_eq!(expected, actual);
+
+        let frame = cache.frame(2);
+        let mut values: Vec<((i32, i32), char)> = frame.iter().map(|(c, v)| (c, *v)).collect();
+        values.sort();
+        assert_eq!(
+            values,
+            vec![((0, 0), 'a'), ((0, 1), 'b'), ((1, 0), 'a'), ((1, 1), 'b')]
+        );
+    }
+
+    #[test]
+    pub fn diffcache_keyframes() {
+        let mut cache: DiffCache<(i32, i32), usize, usize> =
+            DiffCache::new(0).with_keyframe_interval(3);
+        for index in 1..20 {
+            cache.push((index as i32 % 4, 0), index, index).unwrap();
+            cache
+                .push((0, 1 + index as i32 % 3), index, index * 10)
+                .unwrap();
+        }
+        let coords: Vec<(i32, i32)> = (0..4).flat_map(|x| (0..4).map(move |y| (x, y))).collect();
+        let check = |cache: &DiffCache<(i32, i32), usize, usize>| {
+            for index in 0..22 {
+                let frame = cache.frame(index);
+                coords
+                    .iter()
+                    .for_each(|c| assert_eq!(frame.get(c), cache.search_or_default(*c, index)));
+            }
+        };
+        assert!(cache.keyframes.len() >= 5);
+        check(&cache);
+
+        // States stored in the past drop the keyframes after them
+        cache.push((3, 3), 7, 70).unwrap();
+        assert_eq!(cache.keyframes.keys().next_back(), Some(&6));
+        cache.overwrite((1, 0), 5, 50);
+        assert_eq!(cache.keyframes.keys().next_back(), Some(&6));
+        cache.overwrite((2, 2), 4, 40);
+        assert_eq!(cache.keyframes.keys().next_back(), Some(&3));
+        check(&cache);
+        assert_eq!(cache.frame(8).get(&(3, 3)), &70);
+
+        // and they are stored again as new indices come
+        for index in 20..30 {
+            cache.push((2, 2), index, index).unwrap();
+        }
+        assert!(cache.keyframes.keys().next_back() > Some(&20));
+        check(&cache);
+    }
+
+    #[test]
+    pub fn diffcache_storage() {
+        let mut bounds = Bounds::new(0, 0);
+        bounds.include(3, 3);
+        let storages = vec![
+            AnyStorage::default(),
+            AnyStorage::sparse(),
+            AnyStorage::dense(bounds),
+        ];
+        for storage in storages {
+            let mut cache: DiffCache<(i32, i32), usize, usize> =
+                DiffCache::with_storage(0, storage).with_keyframe_interval(2);
+            for index in 0..10 {
+                (0..5).for_each(|x| cache.push((x, index as i32 % 5), index, index).unwrap());
+            }
+            cache.push((-3, 8), 10, 10).unwrap();
+
+            assert_eq!(cache.search((2, 3), 9), Some(&8));
+            assert_eq!(cache.search((-3, 8), 10), Some(&10));
+            assert_eq!(cache.search((5, 5), 10), None);
+            assert_eq!(cache.frame(7).get(&(4, 1)), &6);
+            // The keyframes are laid out as the grid of the storage
+            let keyframe = cache.keyframes.values().next_back().unwrap();
+            assert_eq!(keyframe.grid, cache.data.grid());
+            assert_eq!(cache.coords().count(), 26);
+            let mut changed: Vec<&(i32, i32)> = cache.changed_at(3).collect();
+            changed.sort();
+            assert_eq!(changed, vec![&(0, 3), &(1, 3), &(2, 3), &(3, 3), &(4, 3)]);
+        }
+    }
+
+    #[test]
+    pub fn diffcache_memory_budget() {
+        // (x, 1) keep the same value, (1, 0) changes at every index
+        let push = |cache: &mut DiffCache<(i32, i32), usize, usize>, index: usize| {
+            (0..5).for_each(|x| cache.push((x, 1), index, 7).unwrap());
+            cache.push((1, 0), index, index).unwrap();
+        };
+        let mut reference = DiffCache::new(0).with_keyframe_interval(1000);
+        (0..55).for_each(|index| push(&mut reference, index));
+        let budget = reference.memory_usage();
+
+        let mut cache = DiffCache::new(0)
+            .with_keyframe_interval(1000)
+            .with_memory_budget(budget);
+        (0..60).for_each(|index| push(&mut cache, index));
+        // Merging the identical states is enough, the ones stored since are kept
+        assert!(cache.history(&(0, 1)).count() < 20);
+        assert_eq!(cache.history(&(1, 0)).count(), 60);
+        assert_eq!(cache.decimated().count(), 0);
+        assert_eq!(cache.changed_at(5).collect::<Vec<_>>(), vec![&(1, 0)]);
+        assert_eq!(cache.frame(40).get(&(0, 1)), &7);
+
+        for index in 60..200 {
+            cache.push((1, 0), index, index).unwrap();
+        }
+        assert!(cache.memory_usage() <= budget);
+        let decimated: Vec<Range<usize>> = cache.decimated().collect();
+        assert_eq!(decimated.len(), 1);
+        let Range { start, end } = decimated[0].clone();
+        assert_eq!(start, 0);
+        // The recent history is kept as is, the decimated frames show an older
+        // value, or the default one before the first kept state
+        (end..200).for_each(|index| assert_eq!(cache.search((1, 0), index), Some(&index)));
+        (start..end).for_each(|index| assert!(*cache.search_or_default((1, 0), index) <= index));
+        (end..200).for_each(|index| assert_eq!(cache.search((0, 1), index), Some(&7)));
+        for index in 0..200 {
+            let frame = cache.frame(index);
+            assert_eq!(frame.get(&(1, 0)), cache.search_or_default((1, 0), index));
+        }
+    }
+
+    #[test]
+    pub fn diffcache_memory_budget_backoff() {
+        let mut cache: DiffCache<(i32, i32), usize, usize> =
+            DiffCache::new(0).with_memory_budget(1);
+        for index in 0..100 {
+            cache.push((0, 0), index, index).unwrap();
+        }
+        // The history is reduced as much as possible, but cannot get under
+        // budget: it is only reduced again once grown
+        assert!(cache.changes.len() < 10);
+        assert!(cache.budget.as_ref().unwrap().retry_above > 1);
+    }
+
+    #[test]
+    /// Pushing twice at the same coordinates and index should fail,
+    /// overwriting should not
+    pub fn diffcache_push_duplicate() {
+        let mut cache: DiffCache<(i32, i32), usize, char> = DiffCache::new('.');
+        cache.push((0, 0), 0, 'a').unwrap();
+        assert_eq!(
+            cache.push((0, 0), 0, 'b'),
+            Err(StorageError::IndexAlreadyUsed(0))
+        );
+        assert_eq!(cache.search((0, 0), 0), Some(&'a'));
+
+        assert_eq!(cache.overwrite((0, 0), 0, 'b'), Some('a'));
+        assert_eq!(cache.search((0, 0), 0), Some(&'b'));
+
+        let infos = vec![((1, 0), 1, 'c'), ((1, 0), 1, 'd')];
+        assert!(cache.append(infos.clone().into_iter()).is_err());
+        cache.append_overwrite(infos.into_iter());
+        assert_eq!(cache.search((1, 0), 1), Some(&'d'));
     }
 }
@@ -0,0 +1,22 @@
+use std::error::Error;
+use std::fmt::{self, Debug, Display};
+
+/// Errors that can happen when storing states in a `StateTree`
+/// or in a `DiffCache`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StorageError<K> {
+    /// There is already a state stored at this index
+    IndexAlreadyUsed(K),
+}
+
+impl<K: Debug> Display for StorageError<K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StorageError::IndexAlreadyUsed(index) => {
+                write!(f, "Already got a state at index {:?}", index)
+            }
+        }
+    }
+}
+
+impl<K: Debug> Error for StorageError<K> {}
@@ -0,0 +1,192 @@
+use crate::cell::{Cell, Displayable};
+use crate::coords::{Bounds, Coords};
+use crate::diff_cache::DiffCache;
+use crate::export::raster::color_to_rgb;
+use cursive::theme::Effect;
+use std::io::{self, Write};
+use std::ops::RangeInclusive;
+
+/// Options of the asciicast export
+#[derive(Debug, Clone, PartialEq)]
+pub struct CastOptions {
+    /// Delay between two frames, in seconds
+    pub delay: f64,
+    /// Title of the recording, if any
+    pub title: Option<String>,
+}
+
+impl Default for CastOptions {
+    fn default() -> Self {
+        CastOptions {
+            delay: 0.1,
+            title: None,
+        }
+    }
+}
+
+/// Exports the frames of the DiffCache at every index of the given range
+/// to an asciicast v2 recording (cf. https://docs.asciinema.org/manual/asciicast/v2/).
+///
+/// The first frame is drawn entirely, then each following frame only redraws
+/// the cells that changed at its index.
+/// The terminal size is the bounding box of all the coordinates of the cache.
+/// Fails if the cache is empty.
+pub fn export_asciicast<C, V, W>(
+    cache: &DiffCache<C, usize, V>,
+    indexes: RangeInclusive<usize>,
+    options: &CastOptions,
+    mut writer: W,
+) -> io::Result<()>
+where
+    C: Coords,
+    V: Displayable,
+    W: Write,
+{
+    let bounds = Bounds::of(cache.coords())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Nothing to export"))?;
+
+    // Header
+    write!(
+        writer,
+        "{{\"version\": 2, \"width\": {}, \"height\": {}",
+        bounds.width(),
+        bounds.height()
+    )?;
+    if let Some(ref title) = options.title {
+        write!(writer, ", \"title\": {}", json_string(title))?;
+    }
+    writeln!(writer, "}}")?;
+
+    let (first, last) = (*indexes.start(), *indexes.end());
+    if first > last {
+        return Ok(());
+    }
+
+    // First frame: clears the screen, hides the cursor and draws every cell
+    let mut output = String::from("\x1b[2J\x1b[?25l");
+    let frame = cache.frame(first);
+    (bounds.min.1..=bounds.max.1)
+        .flat_map(|y| (bounds.min.0..=bounds.max.0).map(move |x| (x, y)))
+        .filter_map(|(x, y)| C::from_xy(x, y))
+        .for_each(|c| {
+            let cell = frame.get(&c).to_cell();
+            output.push_str(&draw_cell(&bounds, &c, &cell));
+        });
+    write_event(&mut writer, 0.0, &output)?;
+
+    // Following frames: only the cells that changed
+    for index in first + 1..=last {
+        let output: String = cache
+            .changed_at(index)
+            .map(|c| {
+                let cell = cache.search_or_default(c.clone(), index).to_cell();
+                draw_cell(&bounds, c, &cell)
+            })
+            .collect();
+        if !output.is_empty() {
+            let time = (index - first) as f64 * options.delay;
+            write_event(&mut writer, time, &output)?;
+        }
+    }
+    Ok(())
+}
+
+/// Writes an output event of the recording
+fn write_event<W: Write>(writer: &mut W, time: f64, output: &str) -> io::Result<()> {
+    writeln!(writer, "[{:.6}, \"o\", {}]", time, json_string(output))
+}
+
+/// ANSI sequence moving the cursor to the given coordinates and printing the Cell
+fn draw_cell<C: Coords>(bounds: &Bounds, coords: &C, cell: &Cell) -> String {
+    let row = coords.y() - bounds.min.1 + 1;
+    let col = coords.x() - bounds.min.0 + 1;
+    format!("\x1b[{};{}H{}{}\x1b[0m", row, col, sgr(cell), cell.glyph)
+}
+
+/// ANSI Select Graphic Rendition sequence applying the colours and effects of the Cell
+fn sgr(cell: &Cell) -> String {
+    let mut params: Vec<String> = cell
+        .effects
+        .iter()
+        .filter_map(|e| match e {
+            Effect::Simple => None,
+            Effect::Bold => Some("1".to_string()),
+            Effect::Italic => Some("3".to_string()),
+            Effect::Underline => Some("4".to_string()),
+            Effect::Reverse => Some("7".to_string()),
+        })
+        .collect();
+    if let Some(fg) = cell.fg {
+        let [r, g, b] = color_to_rgb(fg);
+        params.push(format!("38;2;{};{};{}", r, g, b));
+    }
+    if let Some(bg) = cell.bg {
+        let [r, g, b] = color_to_rgb(bg);
+        params.push(format!("48;2;{};{};{}", r, g, b));
+    }
+
+    if params.is_empty() {
+        return String::new();
+    }
+    format!("\x1b[{}m", params.join(";"))
+}
+
+/// Encodes the string as a JSON string literal
+fn json_string(s: &str) -> String {
+    let mut encoded = String::with_capacity(s.len() + 2);
+    encoded.push('"');
+    s.chars().for_each(|c| match c {
+        '"' => encoded.push_str("\\\""),
+        '\\' => encoded.push_str("\\\\"),
+        '\n' => encoded.push_str("\\n"),
+        '\r' => encoded.push_str("\\r"),
+        '\t' => encoded.push_str("\\t"),
+        c if (c as u32) < 0x20 => encoded.push_str(&format!("\\u{:04x}", c as u32)),
+        c => encoded.push(c),
+    });
+    encoded.push('"');
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cursive::theme::{BaseColor, Color};
+
+    #[test]
+    pub fn json_string_escape() {
+        assert_eq!(json_string("a\"b\\c\n"), "\"a\\\"b\\\\c\\n\"");
+        assert_eq!(json_string("\x1b[0m"), "\"\\u001b[0m\"");
+    }
+
+    #[test]
+    pub fn sgr_cell() {
+        assert_eq!(sgr(&Cell::new('a')), "");
+        let cell = Cell::new('a')
+            .fg(Color::Rgb(1, 2, 3))
+            .bg(Color::Dark(BaseColor::Black))
+            .effect(Effect::Bold);
+        assert_eq!(sgr(&cell), "\x1b[1;38;2;1;2;3;48;2;0;0;0m");
+    }
+
+    #[test]
+    pub fn export_asciicast_events() {
+        let mut cache: DiffCache<(i32, i32), usize, char> = DiffCache::new(' ');
+        let infos = vec![((-1, 0), 0, '#'), ((0, 0), 0, '.'), ((0, 0), 2, '@')];
+        cache.append(infos.into_iter()).unwrap();
+
+        let mut output: Vec<u8> = Vec::new();
+        export_asciicast(&cache, 0..=2, &CastOptions::default(), &mut output).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        let lines: Vec<&str> = output.lines().collect();
+
+        // Nothing changed at index 1, so there is no event for it
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "{\"version\": 2, \"width\": 2, \"height\": 1}");
+        assert_eq!(
+            lines[1],
+            "[0.000000, \"o\", \"\\u001b[2J\\u001b[?25l\\u001b[1;1H#\\u001b[0m\\u001b[1;2H.\\u001b[0m\"]"
+        );
+        assert_eq!(lines[2], "[0.200000, \"o\", \"\\u001b[1;2H@\\u001b[0m\"]");
+    }
+}
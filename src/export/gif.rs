@@ -0,0 +1,213 @@
+use crate::cell::Displayable;
+use crate::coords::{Bounds, Coords};
+use crate::diff_cache::DiffCache;
+use crate::export::raster::{Canvas, Raster, Rgb};
+use ::gif::{Encoder, Frame, Repeat, SetParameter};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io::{self, Write};
+use std::ops::RangeInclusive;
+
+/// Maximum number of colours in a GIF palette
+const MAX_PALETTE_SIZE: usize = 256;
+
+/// Options of the GIF export
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GifOptions {
+    /// How cells are turned into pixels
+    pub raster: Raster,
+    /// Size multiplier of a pixel
+    pub scale: usize,
+    /// Delay between two frames, in hundredths of a second
+    pub delay: u16,
+}
+
+impl Default for GifOptions {
+    fn default() -> Self {
+        GifOptions {
+            raster: Raster::Font,
+            scale: 2,
+            delay: 10,
+        }
+    }
+}
+
+/// Exports the frames of the DiffCache at every index of the given range
+/// to an animated GIF.
+///
+/// The exported area is the bounding box of all the coordinates of the cache.
+/// Fails if the cache is empty, or if the image would be too big for a GIF.
+pub fn export_gif<C, V, W>(
+    cache: &DiffCache<C, usize, V>,
+    indexes: RangeInclusive<usize>,
+    options: &GifOptions,
+    writer: W,
+) -> io::Result<()>
+where
+    C: Coords,
+    V: Displayable,
+    W: Write,
+{
+    let bounds = Bounds::of(cache.coords())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Nothing to export"))?;
+    let (width, height) = gif_size(&bounds, options)?;
+
+    let mut encoder = Encoder::new(writer, width, height, &[])?;
+    encoder.set(Repeat::Infinite)?;
+
+    for index in indexes {
+        let canvas = render_frame(cache, &bounds, index, options)?;
+        let mut frame = to_gif_frame(&canvas, width, height);
+        frame.delay = options.delay;
+        encoder.write_frame(&frame)?;
+    }
+    Ok(())
+}
+
+/// Rasterizes the frame of the DiffCache at the given index, inside the given bounds.
+/// Fails if the image would be too big for a GIF
+pub fn render_frame<C, V>(
+    cache: &DiffCache<C, usize, V>,
+    bounds: &Bounds,
+    index: usize,
+    options: &GifOptions,
+) -> io::Result<Canvas>
+where
+    C: Coords,
+    V: Displayable,
+{
+    let scale = options.scale.max(1);
+    let (cell_width, cell_height) = options.raster.cell_size(scale);
+    let (width, height) = gif_size(bounds, options)?;
+    let mut canvas = Canvas::new(usize::from(width), usize::from(height));
+
+    let frame = cache.frame(index);
+    (bounds.min.1..=bounds.max.1)
+        .flat_map(|y| (bounds.min.0..=bounds.max.0).map(move |x| (x, y)))
+        .filter_map(|(x, y)| C::from_xy(x, y).map(|c| (c, x, y)))
+        .for_each(|(c, x, y)| {
+            let cell = frame.get(&c).to_cell();
+            let px = (x - bounds.min.0) as usize * cell_width;
+            let py = (y - bounds.min.1) as usize * cell_height;
+            options.raster.draw(&mut canvas, (px, py), &cell, scale);
+        });
+    Ok(canvas)
+}
+
+/// Converts a Canvas to a GIF frame, with its own palette.
+/// Images with too many colours are quantized.
+fn to_gif_frame(canvas: &Canvas, width: u16, height: u16) -> Frame<'static> {
+    let mut palette: HashMap<Rgb, u8> = HashMap::new();
+    let indexed: Option<Vec<u8>> = canvas
+        .pixels
+        .iter()
+        .map(|p| match palette.get(p) {
+            Some(i) => Some(*i),
+            None if palette.len() < MAX_PALETTE_SIZE => {
+                let i = palette.len() as u8;
+                palette.insert(*p, i);
+                Some(i)
+            }
+            None => None,
+        })
+        .collect();
+
+    match indexed {
+        Some(pixels) => {
+            let mut colors: Vec<(Rgb, u8)> = palette.into_iter().collect();
+            colors.sort_by_key(|(_, i)| *i);
+            let palette: Vec<u8> = colors.iter().flat_map(|(rgb, _)| rgb.to_vec()).collect();
+            Frame::from_palette_pixels(width, height, &pixels, &palette, None)
+        }
+        None => {
+            let pixels: Vec<u8> = canvas.pixels.iter().flat_map(|p| p.to_vec()).collect();
+            Frame::from_rgb_speed(width, height, &pixels, 10)
+        }
+    }
+}
+
+/// Size in pixels of the image covering the given bounds, checking that it
+/// fits in a GIF
+fn gif_size(bounds: &Bounds, options: &GifOptions) -> io::Result<(u16, u16)> {
+    let (cell_width, cell_height) = options.raster.cell_size(options.scale.max(1));
+    Ok((
+        to_gif_size(bounds.width(), cell_width)?,
+        to_gif_size(bounds.height(), cell_height)?,
+    ))
+}
+
+/// Checks that the given number of cells, of the given size in pixels, fits in a GIF
+fn to_gif_size(cells: u64, cell_size: usize) -> io::Result<u16> {
+    usize::try_from(cells)
+        .ok()
+        .and_then(|cells| cells.checked_mul(cell_size))
+        .and_then(|size| u16::try_from(size).ok())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Image too big to be exported to a GIF",
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn export_gif_frames() {
+        let mut cache: DiffCache<(i32, i32), usize, char> = DiffCache::new(' ');
+        let infos = vec![((0, 0), 1, '#'), ((1, 0), 2, '#'), ((0, 0), 3, '.')];
+        cache.append(infos.into_iter()).unwrap();
+
+        let mut output: Vec<u8> = Vec::new();
+        export_gif(&cache, 0..=3, &GifOptions::default(), &mut output).unwrap();
+
+        assert_eq!(&output[0..6], b"GIF89a");
+        // Logical screen: 2x1 cells of 8x12 pixels
+        assert_eq!(&output[6..10], &[16, 0, 12, 0]);
+        // One graphic control extension per frame
+        let frames = output.windows(2).filter(|w| w == &[0x21, 0xF9]).count();
+        assert_eq!(frames, 4);
+    }
+
+    #[test]
+    pub fn export_gif_empty() {
+        let cache: DiffCache<(i32, i32), usize, char> = DiffCache::new(' ');
+        let mut output: Vec<u8> = Vec::new();
+        assert!(export_gif(&cache, 0..=3, &GifOptions::default(), &mut output).is_err());
+    }
+
+    #[test]
+    pub fn render_frame_negative_coords() {
+        let mut cache: DiffCache<(i32, i32), usize, char> = DiffCache::new(' ');
+        cache.push((-1, -1), 0, '#').unwrap();
+        cache.push((1, 0), 0, '#').unwrap();
+        let bounds = Bounds::of(cache.coords()).unwrap();
+        let options = GifOptions {
+            raster: Raster::Palette,
+            scale: 1,
+            delay: 0,
+        };
+
+        let canvas = render_frame(&cache, &bounds, 0, &options).unwrap();
+        assert_eq!((canvas.width, canvas.height), (3, 2));
+        let lit: Vec<bool> = canvas.pixels.iter().map(|p| *p != [0, 0, 0]).collect();
+        assert_eq!(lit, vec![true, false, false, false, false, true]);
+    }
+
+    #[test]
+    pub fn export_gif_too_big() {
+        let mut cache: DiffCache<(i64, i64), usize, char> = DiffCache::new(' ');
+        cache.push((0, 0), 0, '#').unwrap();
+        cache.push((i64::MAX / 2, 0), 0, '#').unwrap();
+        let bounds = Bounds::of(cache.coords()).unwrap();
+        let options = GifOptions::default();
+
+        // The size in pixels overflows, instead of only being too big
+        let rendered = render_frame(&cache, &bounds, 0, &options);
+        assert!(rendered.is_err_and(|e| e.kind() == io::ErrorKind::InvalidInput));
+        let exported = export_gif(&cache, 0..=0, &options, Vec::new());
+        assert_eq!(exported.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+}
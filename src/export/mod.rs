@@ -0,0 +1,3 @@
+pub mod asciicast;
+pub mod gif;
+pub mod raster;
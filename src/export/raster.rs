@@ -0,0 +1,262 @@
+use crate::cell::Cell;
+use cursive::theme::{BaseColor, Color};
+
+/// A RGB colour
+pub type Rgb = [u8; 3];
+
+/// Colour used when a Cell does not specify a background colour
+const DEFAULT_BG: Rgb = [0, 0, 0];
+
+/// Colour used when a Cell does not specify a foreground colour
+const DEFAULT_FG: Rgb = [255, 255, 255];
+
+/// Colours given to glyphs that do not specify any colour, in Palette mode
+const GLYPH_PALETTE: [Rgb; 12] = [
+    [230, 25, 75],
+    [60, 180, 75],
+    [255, 225, 25],
+    [0, 130, 200],
+    [245, 130, 48],
+    [145, 30, 180],
+    [70, 240, 240],
+    [240, 50, 230],
+    [210, 245, 60],
+    [250, 190, 190],
+    [0, 128, 128],
+    [170, 110, 40],
+];
+
+/// Width of a glyph of the built-in font, in pixels
+const GLYPH_WIDTH: usize = 3;
+
+/// Height of a glyph of the built-in font, in pixels
+const GLYPH_HEIGHT: usize = 5;
+
+/// How Cells are turned into pixels
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Raster {
+    /// Draws the glyph of each Cell using a built-in 3x5 bitmap font
+    Font,
+    /// Draws each Cell as a single coloured square: its background colour,
+    /// or its foreground colour, or a colour picked from its glyph
+    Palette,
+}
+
+impl Raster {
+    /// Size (width, height) of a Cell once rasterized, in pixels
+    pub fn cell_size(self, scale: usize) -> (usize, usize) {
+        match self {
+            // One pixel of spacing on the right and at the bottom of each glyph
+            Raster::Font => ((GLYPH_WIDTH + 1) * scale, (GLYPH_HEIGHT + 1) * scale),
+            Raster::Palette => (scale, scale),
+        }
+    }
+
+    /// Draws the Cell on the canvas, its top-left corner being at the
+    /// given pixel position
+    pub fn draw(self, canvas: &mut Canvas, (px, py): (usize, usize), cell: &Cell, scale: usize) {
+        let (width, height) = self.cell_size(scale);
+        match self {
+            Raster::Palette => canvas.fill(px, py, width, height, palette_color(cell)),
+            Raster::Font => {
+                let bg = cell.bg.map_or(DEFAULT_BG, color_to_rgb);
+                let fg = cell.fg.map_or(DEFAULT_FG, color_to_rgb);
+                canvas.fill(px, py, width, height, bg);
+
+                let rows = glyph(cell.glyph);
+                (0..GLYPH_HEIGHT)
+                    .flat_map(|y| (0..GLYPH_WIDTH).map(move |x| (x, y)))
+                    .filter(|(x, y)| rows[*y] & (1 << (GLYPH_WIDTH - 1 - x)) != 0)
+                    .for_each(|(x, y)| {
+                        canvas.fill(px + x * scale, py + y * scale, scale, scale, fg)
+                    });
+            }
+        }
+    }
+}
+
+/// A RGB image being drawn
+pub struct Canvas {
+    /// Width of the image, in pixels
+    pub width: usize,
+    /// Height of the image, in pixels
+    pub height: usize,
+    /// Pixels of the image, row by row
+    pub pixels: Vec<Rgb>,
+}
+
+impl Canvas {
+    /// Creates a new Canvas filled with the default background colour
+    pub fn new(width: usize, height: usize) -> Self {
+        Canvas {
+            width,
+            height,
+            pixels: vec![DEFAULT_BG; width * height],
+        }
+    }
+
+    /// Fills a rectangle of the Canvas with the given colour.
+    /// Pixels outside of the Canvas are ignored.
+    pub fn fill(&mut self, x: usize, y: usize, width: usize, height: usize, color: Rgb) {
+        let max_x = (x + width).min(self.width);
+        let max_y = (y + height).min(self.height);
+        (y..max_y).for_each(|py| {
+            let row = py * self.width;
+            self.pixels[row + x.min(max_x)..row + max_x]
+                .iter_mut()
+                .for_each(|p| *p = color);
+        });
+    }
+}
+
+/// Converts a cursive Color to RGB, using the usual xterm values
+/// for the base colours
+pub fn color_to_rgb(color: Color) -> Rgb {
+    match color {
+        Color::TerminalDefault => DEFAULT_BG,
+        Color::Rgb(r, g, b) => [r, g, b],
+        Color::RgbLowRes(r, g, b) => [r * 51, g * 51, b * 51],
+        Color::Dark(base) => match base {
+            BaseColor::Black => [0, 0, 0],
+            BaseColor::Red => [205, 0, 0],
+            BaseColor::Green => [0, 205, 0],
+            BaseColor::Yellow => [205, 205, 0],
+            BaseColor::Blue => [0, 0, 238],
+            BaseColor::Magenta => [205, 0, 205],
+            BaseColor::Cyan => [0, 205, 205],
+            BaseColor::White => [229, 229, 229],
+        },
+        Color::Light(base) => match base {
+            BaseColor::Black => [127, 127, 127],
+            BaseColor::Red => [255, 0, 0],
+            BaseColor::Green => [0, 255, 0],
+            BaseColor::Yellow => [255, 255, 0],
+            BaseColor::Blue => [92, 92, 255],
+            BaseColor::Magenta => [255, 0, 255],
+            BaseColor::Cyan => [0, 255, 255],
+            BaseColor::White => [255, 255, 255],
+        },
+    }
+}
+
+/// Colour of a Cell in Palette mode
+fn palette_color(cell: &Cell) -> Rgb {
+    match (cell.bg, cell.fg, cell.glyph) {
+        (Some(bg), _, _) => color_to_rgb(bg),
+        (None, Some(fg), _) => color_to_rgb(fg),
+        (None, None, ' ') => DEFAULT_BG,
+        (None, None, glyph) => GLYPH_PALETTE[glyph as usize % GLYPH_PALETTE.len()],
+    }
+}
+
+/// Rows of the built-in 3x5 font for the given glyph, the most significant
+/// of the 3 bits being the leftmost pixel.
+/// Lowercase letters are drawn as uppercase ones, unknown glyphs as a full block.
+fn glyph(c: char) -> [u8; GLYPH_HEIGHT] {
+    match c.to_ascii_uppercase() {
+        ' ' => [0, 0, 0, 0, 0],
+        '0' => [7, 5, 5, 5, 7],
+        '1' => [2, 6, 2, 2, 7],
+        '2' => [7, 1, 7, 4, 7],
+        '3' => [7, 1, 7, 1, 7],
+        '4' => [5, 5, 7, 1, 1],
+        '5' => [7, 4, 7, 1, 7],
+        '6' => [7, 4, 7, 5, 7],
+        '7' => [7, 1, 2, 2, 2],
+        '8' => [7, 5, 7, 5, 7],
+        '9' => [7, 5, 7, 1, 7],
+        'A' => [2, 5, 7, 5, 5],
+        'B' => [6, 5, 6, 5, 6],
+        'C' => [3, 4, 4, 4, 3],
+        'D' => [6, 5, 5, 5, 6],
+        'E' => [7, 4, 6, 4, 7],
+        'F' => [7, 4, 6, 4, 4],
+        'G' => [3, 4, 5, 5, 3],
+        'H' => [5, 5, 7, 5, 5],
+        'I' => [7, 2, 2, 2, 7],
+        'J' => [1, 1, 1, 5, 2],
+        'K' => [5, 5, 6, 5, 5],
+        'L' => [4, 4, 4, 4, 7],
+        'M' => [5, 7, 7, 5, 5],
+        'N' => [6, 5, 5, 5, 5],
+        'O' => [2, 5, 5, 5, 2],
+        'P' => [6, 5, 6, 4, 4],
+        'Q' => [2, 5, 5, 6, 3],
+        'R' => [6, 5, 6, 5, 5],
+        'S' => [3, 4, 2, 1, 6],
+        'T' => [7, 2, 2, 2, 2],
+        'U' => [5, 5, 5, 5, 7],
+        'V' => [5, 5, 5, 5, 2],
+        'W' => [5, 5, 7, 7, 5],
+        'X' => [5, 5, 2, 5, 5],
+        'Y' => [5, 5, 2, 2, 2],
+        'Z' => [7, 1, 2, 4, 7],
+        '#' => [5, 7, 5, 7, 5],
+        '.' => [0, 0, 0, 0, 2],
+        ',' => [0, 0, 0, 2, 4],
+        ':' => [0, 2, 0, 2, 0],
+        '@' => [2, 5, 7, 4, 3],
+        '*' => [0, 5, 2, 5, 0],
+        '+' => [0, 2, 7, 2, 0],
+        '-' => [0, 0, 7, 0, 0],
+        '=' => [0, 7, 0, 7, 0],
+        '_' => [0, 0, 0, 0, 7],
+        '|' => [2, 2, 2, 2, 2],
+        '/' => [1, 1, 2, 4, 4],
+        '\\' => [4, 4, 2, 1, 1],
+        '<' => [1, 2, 4, 2, 1],
+        '>' => [4, 2, 1, 2, 4],
+        '^' => [2, 5, 0, 0, 0],
+        '!' => [2, 2, 2, 0, 2],
+        '?' => [7, 1, 2, 0, 2],
+        '(' => [1, 2, 2, 2, 1],
+        ')' => [4, 2, 2, 2, 4],
+        '[' => [3, 2, 2, 2, 3],
+        ']' => [6, 2, 2, 2, 6],
+        '%' => [5, 1, 2, 4, 5],
+        '~' => [0, 3, 6, 0, 0],
+        _ => [7, 7, 7, 7, 7],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn raster_palette() {
+        let mut canvas = Canvas::new(4, 2);
+        let cell = Cell::new('#').bg(Color::Rgb(1, 2, 3));
+        Raster::Palette.draw(&mut canvas, (2, 0), &cell, 2);
+
+        assert_eq!(canvas.pixels[0], DEFAULT_BG);
+        assert_eq!(canvas.pixels[2], [1, 2, 3]);
+        assert_eq!(canvas.pixels[7], [1, 2, 3]);
+    }
+
+    #[test]
+    pub fn raster_font() {
+        let (width, height) = Raster::Font.cell_size(1);
+        let mut canvas = Canvas::new(width, height);
+        Raster::Font.draw(&mut canvas, (0, 0), &Cell::new('l'), 1);
+
+        // 'L' is a vertical bar on the left, then an horizontal one at the bottom
+        let lit: Vec<bool> = canvas.pixels.iter().map(|p| *p == DEFAULT_FG).collect();
+        assert!((0..5).all(|y| lit[y * width]));
+        assert!((0..3).all(|x| lit[4 * width + x]));
+        assert!(!lit[1]);
+        // Spacing column and row stay empty
+        assert!((0..height).all(|y| !lit[y * width + 3]));
+        assert!((0..width).all(|x| !lit[5 * width + x]));
+    }
+
+    #[test]
+    pub fn raster_canvas_clip() {
+        let mut canvas = Canvas::new(2, 2);
+        canvas.fill(1, 1, 5, 5, [9, 9, 9]);
+        assert_eq!(
+            canvas.pixels,
+            vec![DEFAULT_BG, DEFAULT_BG, DEFAULT_BG, [9, 9, 9]]
+        );
+    }
+}
@@ -0,0 +1,67 @@
+use crate::args::{Args, ArgsError};
+use std::fs;
+use std::io::{self, Read};
+use std::path::PathBuf;
+
+/// Where the puzzle input given to the user's function comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputSource {
+    /// Input provided directly as a String
+    Raw(String),
+    /// Input read from the file at the given path
+    File(PathBuf),
+    /// Input read from the standard input, until EOF
+    Stdin,
+}
+
+impl InputSource {
+    /// Builds an InputSource from the command-line arguments of the binary.
+    /// If no input is given, the input is an empty String.
+    /// cf. `Args::parse`
+    pub fn from_args() -> Result<Self, ArgsError> {
+        Ok(Args::from_env()?.input.unwrap_or_default())
+    }
+
+    /// Reads the whole input, consuming the source
+    pub fn read(self) -> io::Result<String> {
+        match self {
+            InputSource::Raw(input) => Ok(input),
+            InputSource::File(path) => fs::read_to_string(path),
+            InputSource::Stdin => {
+                let mut input = String::new();
+                io::stdin().read_to_string(&mut input)?;
+                Ok(input)
+            }
+        }
+    }
+}
+
+impl Default for InputSource {
+    fn default() -> Self {
+        InputSource::Raw(String::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn input_source_raw() {
+        let input = InputSource::Raw("1\n2\n3".into());
+        assert_eq!(input.read().unwrap(), "1\n2\n3");
+    }
+
+    #[test]
+    pub fn input_source_file() {
+        let input = InputSource::File("examples/input/2018_day1.txt".into());
+        let content = input.read().unwrap();
+        assert!(content.lines().all(|l| l.parse::<i32>().is_ok()));
+    }
+
+    #[test]
+    pub fn input_source_missing_file() {
+        let input = InputSource::File("examples/input/does_not_exist.txt".into());
+        assert!(input.read().is_err());
+    }
+}
@@ -1,6 +1,22 @@
 pub mod aoc_viz_app;
+pub mod args;
+pub mod breakpoint;
+pub mod cargo_aoc;
+pub mod cell;
+pub mod coords;
 pub mod diff_cache;
+pub mod error;
+pub mod export;
+pub mod input;
+#[cfg(feature = "serde")]
+pub mod recording;
+pub mod shared_cache;
+#[cfg(feature = "serde")]
+pub mod snapshot;
 pub mod state_tree;
+pub mod storage;
 pub mod time_index;
 pub mod view;
 pub mod visualize;
+
+pub use aoc_viz_macro::aoc_viz;
@@ -0,0 +1,354 @@
+use crate::coords::Coords;
+use crate::shared_cache::SharedDiffCache;
+use crate::snapshot::invalid_data;
+use crate::time_index::{Generation, TimeIndex};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::marker::PhantomData;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// First bytes of a recording
+const MAGIC: &[u8; 8] = b"AOCVIZRC";
+
+/// Version of the recording format, to bump whenever the stored types change
+const VERSION: u32 = 2;
+
+/// Size of the length written before each record
+const LENGTH_SIZE: usize = 4;
+
+/// Amount of bytes read at once from a recording
+const READ_SIZE: usize = 64 * 1024;
+
+/// A frame of a recording: its index and the values that changed
+pub type Frame<C, V> = (usize, Vec<(C, V)>);
+
+/// A record following the default value in a recording
+#[derive(Serialize, Deserialize)]
+enum Record<F> {
+    /// A frame, as its index and the values that changed
+    Frame(F),
+    /// The run is over, no more frames will follow
+    End(Generation),
+}
+
+/// Appends the frames of a run to a recording, as they are produced.
+///
+/// A recording is made of a small header, followed by records encoded with
+/// bincode and prefixed by their length: the default value first, then one
+/// record per frame holding its index and the values that changed, and a
+/// last record telling how the run ended.
+/// Every record is flushed once written, so that a run stopped at any point
+/// leaves a readable recording.
+pub struct RecordingWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> RecordingWriter<W> {
+    /// Creates a new instance of a RecordingWriter, writing the header of the
+    /// recording and the default value of its frames
+    pub fn new<V: Serialize>(mut writer: W, default: &V) -> io::Result<Self> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+        let mut recording = RecordingWriter { writer };
+        recording.write_record(default)?;
+        Ok(recording)
+    }
+
+    /// Appends a frame: its index and the values that changed
+    pub fn write_frame<C, V>(&mut self, index: usize, changes: &[(C, V)]) -> io::Result<()>
+    where
+        C: Serialize,
+        V: Serialize,
+    {
+        self.write_record(&Record::Frame((index, changes)))
+    }
+
+    /// Appends the end of the run, telling how it ended: no frame can follow
+    pub fn write_end(&mut self, generation: Generation) -> io::Result<()> {
+        self.write_record(&Record::<()>::End(generation))
+    }
+
+    /// Appends a record prefixed by its length, and flushes it
+    fn write_record<T: Serialize>(&mut self, record: &T) -> io::Result<()> {
+        let bytes = bincode::serialize(record).map_err(invalid_data)?;
+        let length: u32 = bytes.len().try_into().map_err(invalid_data)?;
+        self.writer.write_all(&length.to_le_bytes())?;
+        self.writer.write_all(&bytes)?;
+        self.writer.flush()
+    }
+}
+
+impl RecordingWriter<BufWriter<File>> {
+    /// Creates the recording file at the given path (cf. `new`)
+    pub fn create<V: Serialize>(path: &Path, default: &V) -> io::Result<Self> {
+        RecordingWriter::new(BufWriter::new(File::create(path)?), default)
+    }
+}
+
+/// Reads the frames of a recording written by a RecordingWriter, of values
+/// of type V on a plane of C coordinates.
+///
+/// Reaching the end of the recording is not an error: the frames written
+/// afterwards, e.g. by a solver still running, are read by the next calls.
+pub struct RecordingReader<R: Read, C, V> {
+    reader: R,
+    /// Bytes read but not decoded yet, for lack of a complete record
+    pending: Pending,
+    /// Value of the coordinates that never changed
+    default: V,
+    /// How the run ended, once its end was read
+    end: Option<Generation>,
+    _phantom_c: PhantomData<C>,
+}
+
+impl<R, C, V> RecordingReader<R, C, V>
+where
+    R: Read,
+    C: Coords + DeserializeOwned,
+    V: DeserializeOwned,
+{
+    /// Creates a new instance of a RecordingReader, reading the header of the
+    /// recording and the default value of its frames
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let mut header = [0; 12];
+        reader.read_exact(&mut header)?;
+        if header[..8] != MAGIC[..] {
+            return Err(invalid_data("Not an aoc-viz recording"));
+        }
+        let version = u32::from_le_bytes(header[8..].try_into().unwrap());
+        if version != VERSION {
+            let message = format!("Unsupported recording version {}", version);
+            return Err(invalid_data(message));
+        }
+
+        let mut pending = Pending::default();
+        let default = pending
+            .next_record(&mut reader)?
+            .ok_or_else(|| io::Error::new(io::ErrorKind::UnexpectedEof, "Missing default value"))?;
+        Ok(RecordingReader {
+            reader,
+            pending,
+            default,
+            end: None,
+            _phantom_c: PhantomData,
+        })
+    }
+
+    /// Value of the coordinates that never changed
+    pub fn default_value(&self) -> &V {
+        &self.default
+    }
+
+    /// How the run that wrote the recording ended, once its end was read.
+    /// None while the run may still write more frames
+    pub fn end(&self) -> Option<Generation> {
+        self.end
+    }
+
+    /// Reads the next frame, as its index and the values that changed.
+    /// Returns None if no complete frame was written yet, or if the
+    /// recording ended (cf. `end`).
+    pub fn next_frame(&mut self) -> io::Result<Option<Frame<C, V>>> {
+        if self.end.is_some() {
+            return Ok(None);
+        }
+        match self.pending.next_record(&mut self.reader)? {
+            Some(Record::Frame(frame)) => Ok(Some(frame)),
+            Some(Record::End(generation)) => {
+                self.end = Some(generation);
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Reads all the frames written so far, appending them to the cache and
+    /// growing the TimeIndex accordingly, then marking its generation as over
+    /// once the recording ended. The frames read before an error are still
+    /// stored.
+    /// Returns the number of frames read.
+    pub fn read_into(
+        &mut self,
+        cache: &SharedDiffCache<C, V>,
+        time_index: &Mutex<TimeIndex>,
+    ) -> io::Result<usize>
+    where
+        V: Clone,
+    {
+        let mut frames = Vec::new();
+        let read = loop {
+            match self.next_frame() {
+                Ok(Some(frame)) => frames.push(frame),
+                Ok(None) => break Ok(frames.len()),
+                Err(e) => break Err(e),
+            }
+        };
+        if let Some(&(last, _)) = frames.last() {
+            // The frames are published before the TimeIndex shows them
+            cache.append(frames);
+            cache.sync();
+            let mut time_index = time_index.lock().unwrap();
+            while time_index.max < last {
+                time_index.add_max();
+            }
+        }
+        if let Some(generation) = self.end {
+            time_index.lock().unwrap().generation = generation;
+        }
+        read
+    }
+}
+
+impl<C, V> RecordingReader<File, C, V>
+where
+    C: Coords + DeserializeOwned,
+    V: DeserializeOwned,
+{
+    /// Opens the recording file at the given path (cf. `new`)
+    pub fn open(path: &Path) -> io::Result<Self> {
+        RecordingReader::new(File::open(path)?)
+    }
+}
+
+/// Bytes read from a recording, of which the ones before `start` were
+/// already decoded. These are only dropped when reading more bytes, so that
+/// decoding many small records does not move the remaining bytes every time
+#[derive(Default)]
+struct Pending {
+    bytes: Vec<u8>,
+    start: usize,
+}
+
+impl Pending {
+    /// Decodes the next record, reading more bytes when the pending ones do
+    /// not hold a complete one. Returns None if there are not enough bytes yet.
+    fn next_record<T: DeserializeOwned>(
+        &mut self,
+        reader: &mut impl Read,
+    ) -> io::Result<Option<T>> {
+        loop {
+            let pending = &self.bytes[self.start..];
+            if pending.len() >= LENGTH_SIZE {
+                let length =
+                    u32::from_le_bytes(pending[..LENGTH_SIZE].try_into().unwrap()) as usize;
+                if pending.len() >= LENGTH_SIZE + length {
+                    let record = bincode::deserialize(&pending[LENGTH_SIZE..LENGTH_SIZE + length])
+                        .map_err(invalid_data)?;
+                    self.start += LENGTH_SIZE + length;
+                    return Ok(Some(record));
+                }
+            }
+
+            self.bytes.drain(..self.start);
+            self.start = 0;
+            let mut buffer = [0; READ_SIZE];
+            match reader.read(&mut buffer) {
+                Ok(0) => return Ok(None),
+                Ok(read) => self.bytes.extend_from_slice(&buffer[..read]),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff_cache::DiffCache;
+    use std::cell::RefCell;
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    type Reader<'a> = RecordingReader<&'a [u8], (i32, i32), char>;
+
+    /// Reads the bytes written so far to a shared buffer
+    struct Tail(Rc<RefCell<VecDeque<u8>>>);
+
+    impl Read for Tail {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.0.borrow_mut().read(buf)
+        }
+    }
+
+    fn record(frames: &[Frame<(i32, i32), char>]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut writer = RecordingWriter::new(&mut bytes, &'.').unwrap();
+        frames
+            .iter()
+            .for_each(|(index, changes)| writer.write_frame(*index, changes).unwrap());
+        bytes
+    }
+
+    #[test]
+    pub fn recording_round_trip() {
+        let frames = vec![
+            (1, vec![((0, 0), 'a'), ((2, 1), 'b')]),
+            (2, vec![]),
+            (3, vec![((0, 0), 'c')]),
+        ];
+        let bytes = record(&frames);
+
+        let mut reader = Reader::new(&bytes[..]).unwrap();
+        assert_eq!(reader.default_value(), &'.');
+        let shared = SharedDiffCache::new(DiffCache::new(*reader.default_value()));
+        let time_index = Mutex::new(TimeIndex::new(0, 0, 0));
+        assert_eq!(reader.read_into(&shared, &time_index).unwrap(), 3);
+        assert_eq!(time_index.lock().unwrap().max, 3);
+        let cache = shared.snapshot();
+        assert_eq!(cache.search_or_default((0, 0), 2), &'a');
+        assert_eq!(cache.search_or_default((0, 0), 3), &'c');
+        assert_eq!(cache.search_or_default((2, 1), 0), &'.');
+        assert_eq!(reader.next_frame().unwrap(), None);
+    }
+
+    #[test]
+    pub fn recording_partial_frame() {
+        let frames = vec![(1, vec![((0, 0), 'a')]), (2, vec![((1, 1), 'b')])];
+        let bytes = record(&frames);
+        let (head, tail) = bytes.split_at(bytes.len() - 3);
+
+        // A recording still being written, the last frame being incomplete
+        let written = Rc::new(RefCell::new(head.iter().cloned().collect()));
+        let mut reader: RecordingReader<_, (i32, i32), char> =
+            RecordingReader::new(Tail(written.clone())).unwrap();
+        assert_eq!(reader.next_frame().unwrap(), Some(frames[0].clone()));
+        assert_eq!(reader.next_frame().unwrap(), None);
+
+        // It is read once complete
+        written.borrow_mut().extend(tail);
+        assert_eq!(reader.next_frame().unwrap(), Some(frames[1].clone()));
+        assert_eq!(reader.next_frame().unwrap(), None);
+    }
+
+    #[test]
+    pub fn recording_end() {
+        let mut bytes = Vec::new();
+        let mut writer = RecordingWriter::new(&mut bytes, &'.').unwrap();
+        writer.write_frame(1, &[((0, 0), 'a')]).unwrap();
+        let generation = Generation::Finished(Duration::from_millis(1500));
+        writer.write_end(generation).unwrap();
+
+        let mut reader = Reader::new(&bytes[..]).unwrap();
+        assert_eq!(reader.end(), None);
+        let shared = SharedDiffCache::new(DiffCache::new('.'));
+        let time_index = Mutex::new(TimeIndex::new(0, 0, 0));
+        assert_eq!(reader.read_into(&shared, &time_index).unwrap(), 1);
+        assert_eq!(reader.end(), Some(generation));
+        assert_eq!(time_index.lock().unwrap().generation, generation);
+        assert_eq!(reader.next_frame().unwrap(), None);
+    }
+
+    #[test]
+    pub fn recording_invalid() {
+        let error = Reader::new(&b"not a recording"[..]).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let error = Reader::new(&MAGIC[..]).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
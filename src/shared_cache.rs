@@ -0,0 +1,239 @@
+use crate::coords::Coords;
+use crate::diff_cache::DiffCache;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Delay between two attempts to publish the changes, while a reader
+/// still holds the copy of the cache they are written to
+const SYNC_DELAY: Duration = Duration::from_millis(1);
+
+/// A DiffCache indexed over time
+type Cache<C, V> = DiffCache<C, usize, V>;
+
+/// A frame, as its index and the values that changed at that index
+pub type FrameChanges<C, V> = (usize, Vec<(C, V)>);
+
+/// A DiffCache indexed over time, shared between the thread populating it
+/// and the views displaying it, which never wait for each other.
+///
+/// Two copies of the cache are kept. Readers take a snapshot of the published
+/// one, an immutable `Arc` that stays valid as long as they need it. Changes
+/// are written to the other copy, which is then published in its turn. If a
+/// reader still holds that other copy, the changes are kept in a log and
+/// written once it is released, by the next write, `publish` or `sync`:
+/// only the writer publishes, readers never write.
+///
+/// Every change is thus applied twice, and the cache takes twice the memory.
+pub struct SharedDiffCache<C: Coords, V> {
+    shared: Arc<Shared<C, V>>,
+}
+
+struct Shared<C: Coords, V> {
+    /// Copy of the cache given to the readers. Only locked to be cloned or swapped
+    published: Mutex<Arc<Cache<C, V>>>,
+    /// Copy of the cache being written and the changes it misses
+    writer: Mutex<Writer<C, V>>,
+}
+
+struct Writer<C: Coords, V> {
+    /// Copy of the cache that is not published. Readers may still hold it,
+    /// if they took it before it was replaced
+    spare: Arc<Cache<C, V>>,
+    /// Changes applied to the published copy only
+    behind: Vec<Change<C, V>>,
+    /// Changes applied to neither copy, until the spare one is released
+    pending: Vec<Change<C, V>>,
+}
+
+/// A change written to both copies of the cache
+enum Change<C: Coords, V> {
+    /// Appends the frames, overwriting the values already stored
+    Append(Vec<FrameChanges<C, V>>),
+    /// Replaces the whole cache
+    Replace(Box<Cache<C, V>>),
+}
+
+impl<C: Coords, V: Clone> Change<C, V> {
+    fn apply(&self, cache: &mut Cache<C, V>) {
+        match self {
+            Change::Append(frames) => frames.iter().for_each(|(index, changes)| {
+                cache.append_overwrite(changes.iter().cloned().map(|(c, v)| (c, *index, v)))
+            }),
+            Change::Replace(replacement) => *cache = (**replacement).clone(),
+        }
+    }
+}
+
+impl<C: Coords, V: Clone> Writer<C, V> {
+    /// Writes the pending changes to the spare copy and publishes it, unless
+    /// a reader still holds it.
+    /// Returns false if some changes are still pending
+    fn publish(&mut self, published: &Mutex<Arc<Cache<C, V>>>) -> bool {
+        if self.pending.is_empty() {
+            return true;
+        }
+        let spare = match Arc::get_mut(&mut self.spare) {
+            Some(spare) => spare,
+            None => return false,
+        };
+        self.behind.drain(..).for_each(|change| change.apply(spare));
+        self.pending.iter().for_each(|change| change.apply(spare));
+        std::mem::swap(&mut *published.lock().unwrap(), &mut self.spare);
+        self.behind = std::mem::take(&mut self.pending);
+        true
+    }
+}
+
+impl<C: Coords, V: Clone> SharedDiffCache<C, V> {
+    /// Creates a new instance of a SharedDiffCache, publishing the given cache
+    pub fn new(cache: Cache<C, V>) -> Self {
+        let writer = Writer {
+            spare: Arc::new(cache.clone()),
+            behind: Vec::new(),
+            pending: Vec::new(),
+        };
+        SharedDiffCache {
+            shared: Arc::new(Shared {
+                published: Mutex::new(Arc::new(cache)),
+                writer: Mutex::new(writer),
+            }),
+        }
+    }
+
+    /// Latest published state of the cache, which can be kept as long as
+    /// needed without blocking the writes
+    pub fn snapshot(&self) -> Arc<Cache<C, V>> {
+        self.shared.published.lock().unwrap().clone()
+    }
+
+    /// Appends the given frames, overwriting the values already stored
+    pub fn append(&self, frames: Vec<FrameChanges<C, V>>) {
+        self.write(Change::Append(frames));
+    }
+
+    /// Replaces the whole cache, dropping the changes not published yet.
+    /// Waits for the new cache to be published (cf. `sync`)
+    pub fn replace(&self, cache: Cache<C, V>) {
+        {
+            let mut writer = self.shared.writer.lock().unwrap();
+            writer.behind.clear();
+            writer.pending.clear();
+            writer.pending.push(Change::Replace(Box::new(cache)));
+        }
+        self.sync();
+    }
+
+    /// Publishes the changes written so far, unless a reader still holds the
+    /// copy they are written to.
+    /// Returns false if some changes are still pending
+    pub fn publish(&self) -> bool {
+        self.shared
+            .writer
+            .lock()
+            .unwrap()
+            .publish(&self.shared.published)
+    }
+
+    /// Publishes every change written so far, waiting for the readers of the
+    /// copy they are written to to release it
+    pub fn sync(&self) {
+        while !self.publish() {
+            std::thread::sleep(SYNC_DELAY);
+        }
+    }
+
+    fn write(&self, change: Change<C, V>) {
+        let mut writer = self.shared.writer.lock().unwrap();
+        writer.pending.push(change);
+        writer.publish(&self.shared.published);
+    }
+}
+
+impl<C: Coords, V> Clone for SharedDiffCache<C, V> {
+    fn clone(&self) -> Self {
+        SharedDiffCache {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn shared_cache_publish() {
+        let shared = SharedDiffCache::new(DiffCache::new('.'));
+        shared.append(vec![(1, vec![((0, 0), 'a')])]);
+        assert_eq!(shared.snapshot().search((0, 0), 1), Some(&'a'));
+
+        // A reader holds each copy: the changes wait for one of them
+        let first = shared.snapshot();
+        shared.append(vec![(2, vec![((0, 0), 'b')])]);
+        let second = shared.snapshot();
+        shared.append(vec![(3, vec![((0, 0), 'c')])]);
+        shared.append(vec![(4, vec![((1, 0), 'd')])]);
+        assert_eq!(first.search((0, 0), 3), Some(&'a'));
+        assert_eq!(second.search((0, 0), 3), Some(&'b'));
+        assert_eq!(shared.snapshot().search((0, 0), 3), Some(&'b'));
+
+        // and are published by the writer once it is released
+        assert!(!shared.publish());
+        drop(first);
+        assert!(shared.publish());
+        let third = shared.snapshot();
+        assert_eq!(third.search((0, 0), 3), Some(&'c'));
+        assert_eq!(third.search((1, 0), 4), Some(&'d'));
+        drop(second);
+        drop(third);
+        shared.append(vec![(5, vec![((1, 0), 'e')])]);
+        let cache = shared.snapshot();
+        let history: Vec<(&usize, &char)> = cache.history(&(0, 0)).collect();
+        assert_eq!(history, vec![(&1, &'a'), (&2, &'b'), (&3, &'c')]);
+        assert_eq!(cache.search((1, 0), 5), Some(&'e'));
+    }
+
+    #[test]
+    pub fn shared_cache_replace() {
+        let shared = SharedDiffCache::new(DiffCache::new('.'));
+        let first = shared.snapshot();
+        shared.append(vec![(1, vec![((0, 0), 'a')])]);
+        let second = shared.snapshot();
+        shared.append(vec![(2, vec![((0, 0), 'b')])]);
+
+        // The replacement drops the pending changes, and waits for a copy to be released
+        let release = std::thread::spawn(move || {
+            std::thread::sleep(SYNC_DELAY * 10);
+            drop(first);
+        });
+        shared.replace(DiffCache::new('#'));
+        release.join().unwrap();
+        assert_eq!(shared.snapshot().search((0, 0), 2), None);
+        drop(second);
+        shared.append(vec![(1, vec![((1, 1), 'b')])]);
+        shared.sync();
+
+        let cache = shared.snapshot();
+        assert_eq!(cache.default_value(), &'#');
+        assert_eq!(cache.search((0, 0), 1), None);
+        assert_eq!(cache.search((1, 1), 1), Some(&'b'));
+    }
+
+    #[test]
+    pub fn shared_cache_threads() {
+        let shared = SharedDiffCache::new(DiffCache::new(0));
+        let writer = shared.clone();
+        let handle = std::thread::spawn(move || {
+            (1..=1000).for_each(|i| writer.append(vec![(i, vec![((0, 0), i)])]));
+            writer.sync();
+        });
+        // Frames are published in order, while being written
+        let mut last = 0;
+        while last < 1000 {
+            let value = *shared.snapshot().search_or_default((0, 0), 1000);
+            assert!(value >= last);
+            last = value;
+        }
+        handle.join().unwrap();
+    }
+}
@@ -0,0 +1,132 @@
+use crate::coords::Coords;
+use crate::diff_cache::DiffCache;
+use crate::time_index::TimeIndex;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+/// First bytes of a snapshot
+const MAGIC: &[u8; 8] = b"AOCVIZ\0\0";
+
+/// Version of the snapshot format, to bump whenever the stored types change
+const VERSION: u32 = 4;
+
+/// Writes a snapshot of the frames stored in the DiffCache, and of the
+/// TimeIndex spanning them, to the writer.
+///
+/// Snapshots are made of a small header followed by the bincode encoding of both.
+pub fn save<C, V>(
+    cache: &DiffCache<C, usize, V>,
+    time_index: &TimeIndex,
+    mut writer: impl Write,
+) -> io::Result<()>
+where
+    C: Coords + Serialize,
+    V: Serialize,
+{
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    bincode::serialize_into(&mut writer, &(cache, time_index)).map_err(invalid_data)?;
+    writer.flush()
+}
+
+/// Reads back a snapshot written by `save`
+pub fn load<C, V>(mut reader: impl Read) -> io::Result<(DiffCache<C, usize, V>, TimeIndex)>
+where
+    C: Coords + DeserializeOwned,
+    V: DeserializeOwned,
+{
+    let mut header = [0; 12];
+    reader.read_exact(&mut header)?;
+    if header[..8] != MAGIC[..] {
+        return Err(invalid_data("Not an aoc-viz snapshot"));
+    }
+    let version = u32::from_le_bytes(header[8..].try_into().unwrap());
+    if version != VERSION {
+        let message = format!("Unsupported snapshot version {}", version);
+        return Err(invalid_data(message));
+    }
+    bincode::deserialize_from(reader).map_err(invalid_data)
+}
+
+/// Writes a snapshot of the frames to the file at the given path (cf. `save`)
+pub fn save_file<C, V>(
+    cache: &DiffCache<C, usize, V>,
+    time_index: &TimeIndex,
+    path: &Path,
+) -> io::Result<()>
+where
+    C: Coords + Serialize,
+    V: Serialize,
+{
+    save(cache, time_index, BufWriter::new(File::create(path)?))
+}
+
+/// Reads back the snapshot written to the file at the given path (cf. `load`)
+pub fn load_file<C, V>(path: &Path) -> io::Result<(DiffCache<C, usize, V>, TimeIndex)>
+where
+    C: Coords + DeserializeOwned,
+    V: DeserializeOwned,
+{
+    load(BufReader::new(File::open(path)?))
+}
+
+/// Error for a snapshot or a recording that can not be encoded or decoded
+pub(crate) fn invalid_data<E>(error: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn snapshot_round_trip() {
+        let mut cache: DiffCache<(i32, i32), usize, char> = DiffCache::new('.');
+        let infos = vec![((0, 0), 0, 'a'), ((-3, 2), 1, 'b'), ((0, 0), 2, 'c')];
+        cache.append(infos.into_iter()).unwrap();
+        let mut time_index = TimeIndex::new(0, 2, 1);
+        time_index.hit_breakpoint(2, "c".to_string());
+
+        let mut bytes = Vec::new();
+        save(&cache, &time_index, &mut bytes).unwrap();
+        let (loaded, loaded_index): (DiffCache<(i32, i32), usize, char>, _) =
+            load(&bytes[..]).unwrap();
+
+        assert_eq!(loaded.search_or_default((0, 0), 1), &'a');
+        assert_eq!(loaded.search_or_default((0, 0), 2), &'c');
+        assert_eq!(loaded.search_or_default((5, 5), 2), &'.');
+        assert_eq!(loaded.bounds(), cache.bounds());
+        assert_eq!(loaded.extent_at(1), cache.extent_at(1));
+        assert_eq!(
+            (loaded_index.min, loaded_index.max, loaded_index.current),
+            (0, 2, 1)
+        );
+        assert_eq!(loaded_index.breaks, time_index.breaks);
+    }
+
+    #[test]
+    pub fn snapshot_invalid() {
+        let load = |bytes: &[u8]| load::<(i32, i32), char>(bytes).map(|_| ());
+        assert_eq!(
+            load(b"not a snapshot").unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+        assert_eq!(
+            load(b"AOCVIZ").unwrap_err().kind(),
+            io::ErrorKind::UnexpectedEof
+        );
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&5u32.to_le_bytes());
+        assert_eq!(
+            load(&bytes).unwrap_err().to_string(),
+            "Unsupported snapshot version 5"
+        );
+    }
+}
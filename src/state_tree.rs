@@ -1,136 +1,220 @@
+use crate::error::StorageError;
 use std::cmp::Ordering;
+#[cfg(feature = "serde")]
+use std::convert::TryFrom;
 use std::fmt::Debug;
 
-/// A Binary Search Tree custom implementation allowing to store the
-/// different states of a set of value over time.
+/// An append-optimized storage allowing to store the different states
+/// of a value over time.
 ///
-/// Accessing an element is O(n) worst case, and O(log n) on average
-/// Storing an element is O(n) worst case, and O(log n) on average
+/// States are kept in a Vec, sorted by index. Since states are usually
+/// pushed in increasing index order, storing an element is O(1) in that
+/// case, and O(n) worst case when inserting in the middle of the history.
+/// Accessing an element is O(log n), using a binary search.
 ///
 /// Time is generic, any K type implementing Ord + Eq + Debug is to
 /// be considered as potentially OK to represent time.
 ///
-/// Value is generic, any V type can be stored in the tree.       
+/// Value is generic, any V type can be stored in the tree.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "StateTreeParts<K, V>"))]
 pub struct StateTree<K: Ord + Eq + Debug, V> {
-    /// The `StateTreeNode`, value contained in this tree's root.
-    node: StateTreeNode<K, V>,
-    /// The lower children (usually represented as the left one)
-    /// cf. Binary Search Tree doc
-    child_low: Option<Box<StateTree<K, V>>>,
-    /// The higher children (usually represented as the right one)
-    /// cf. Binary Search Tree doc
-    child_high: Option<Box<StateTree<K, V>>>,
+    /// The `StateTreeNode`s contained in this tree, sorted by index
+    nodes: Vec<StateTreeNode<K, V>>,
+}
+
+/// Fields of a deserialized StateTree, before checking that its states are
+/// sorted by index
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct StateTreeParts<K, V> {
+    nodes: Vec<StateTreeNode<K, V>>,
+}
+
+#[cfg(feature = "serde")]
+impl<K: Ord + Eq + Debug, V> TryFrom<StateTreeParts<K, V>> for StateTree<K, V> {
+    type Error = &'static str;
+
+    fn try_from(parts: StateTreeParts<K, V>) -> Result<Self, Self::Error> {
+        if parts.nodes.is_empty() {
+            return Err("StateTree without any state");
+        }
+        if parts.nodes.windows(2).any(|n| n[0].indexer >= n[1].indexer) {
+            return Err("StateTree with states not sorted by index");
+        }
+        Ok(StateTree { nodes: parts.nodes })
+    }
 }
 
 impl<K: Ord + Eq + Debug, V> StateTree<K, V> {
     /// Creates a new instance of a StateTree, the index and value.
-    /// Children of a newly created StateTree will be None by default
     pub fn new(first_index: K, first_value: V) -> Self {
         StateTree {
-            node: StateTreeNode {
+            nodes: vec![StateTreeNode {
                 indexer: first_index,
                 value: first_value,
-            },
-            child_low: None,
-            child_high: None,
+            }],
         }
     }
 
-    /// Adds a new node to the tree
-    pub fn push(&mut self, index: K, value: V) {
+    /// Adds a new node to the tree.
+    /// Fails if there is already a state at the given index, because storing
+    /// two different values at the same index does not make sense
+    pub fn push(&mut self, index: K, value: V) -> Result<(), StorageError<K>> {
         let node = StateTreeNode {
             indexer: index,
             value,
         };
-        self.push_inner_non_recursive(node);
-    }
-
-    /// Internal API to the push function, avoids leaking StateTreeNode
-    fn _push_inner(&mut self, node: StateTreeNode<K, V>) {
-        // Compared to this node, the new node is either greater or lower.
-        // This data structure does not support storing two different values
-        // at the same index, because it does not make sense
-        let next_node = match node.indexer.cmp(&self.node.indexer) {
-            Ordering::Greater => &mut self.child_high,
-            Ordering::Less => &mut self.child_low,
-            _ => panic!("Already got a state at index {:?}", node.indexer),
-        };
 
-        // next_node is either None, (no node - so we store node as the next_node)
-        // or contains a reference to a StateTreeNode (so we use push_inner on that one)
-        // eventually storing node at some point
-        match next_node {
-            None => *next_node = Some(Box::new(node.as_root())),
-            Some(ref mut n) => n._push_inner(node),
+        // Fast path: appending after the last known state
+        let last = self.nodes.last().map(|n| node.indexer.cmp(&n.indexer));
+        if last == Some(Ordering::Greater) {
+            self.nodes.push(node);
+            return Ok(());
         }
-    }
 
-    /// Internal API to the push function. Avoids leaking StateTreeNode type.
-    /// Non-recursive version to avoid possible stack overflow
-    fn push_inner_non_recursive(&mut self, node: StateTreeNode<K, V>) {
-        let mut next_node = match node.indexer.cmp(&self.node.indexer) {
-            Ordering::Greater => &mut self.child_high,
-            Ordering::Less => &mut self.child_low,
-            _ => panic!("Already got a state at index {:?}", node.indexer),
-        };
+        match self.position(&node.indexer) {
+            Ok(_) => Err(StorageError::IndexAlreadyUsed(node.indexer)),
+            Err(pos) => {
+                self.nodes.insert(pos, node);
+                Ok(())
+            }
+        }
+    }
 
-        let mut do_continue = true;
-        while do_continue {
-            if let Some(next) = next_node {
-                let cmp = next.node.indexer.cmp(&node.indexer);
-                next_node = match cmp {
-                    Ordering::Less => &mut next.child_high,
-                    Ordering::Greater => &mut next.child_low,
-                    _ => panic!("Already got a state at index {:?}", node.indexer),
-                }
-            } else {
-                do_continue = false;
+    /// Adds a new node to the tree, replacing the state at the given index
+    /// if there was one.
+    /// Returns the replaced value, if any.
+    pub fn overwrite(&mut self, index: K, value: V) -> Option<V> {
+        match self.position(&index) {
+            Ok(pos) => Some(std::mem::replace(&mut self.nodes[pos].value, value)),
+            Err(pos) => {
+                self.nodes.insert(
+                    pos,
+                    StateTreeNode {
+                        indexer: index,
+                        value,
+                    },
+                );
+                None
             }
         }
-        *next_node = Some(Box::new(node.as_root()))
+    }
+
+    /// Binary searches the position of the given index in the inner nodes
+    fn position(&self, index: &K) -> Result<usize, usize> {
+        self.nodes.binary_search_by(|n| n.indexer.cmp(index))
     }
 
     /// Searches for an element in the tree, returning an Option because searching might fail
     ///
     /// Searching returns a reference to the value V indexed by the biggest K such as K <= index
     pub fn search(&self, index: K) -> Option<&V> {
-        match index.cmp(&self.node.indexer) {
-            Ordering::Equal => Some(&self.node.value),
-            Ordering::Less => match self.child_low {
-                Some(ref child) => child.search(index),
-                _ => None,
-            },
-            Ordering::Greater => match self.child_high {
-                Some(ref child) => {
-                    let searched = child.search(index);
-                    if searched.is_none() {
-                        Some(&self.node.value)
-                    } else {
-                        searched
-                    }
+        let pos = self.nodes.partition_point(|n| n.indexer <= index);
+        pos.checked_sub(1).map(|p| &self.nodes[p].value)
+    }
+
+    /// Searches for the value at the given index like `search`, knowing that
+    /// the first `known` states are stored at or before it. The following
+    /// states are scanned, which is faster than searching the whole tree
+    /// when only a few of them are stored before the index
+    pub fn search_from(&self, known: usize, index: &K) -> Option<&V> {
+        let after = self.nodes.get(known..).map_or(0, |nodes| {
+            nodes.iter().take_while(|n| n.indexer <= *index).count()
+        });
+        (known + after)
+            .checked_sub(1)
+            .and_then(|p| self.nodes.get(p))
+            .map(|n| &n.value)
+    }
+
+    /// Smallest index strictly greater than the given one at which a state is stored
+    pub fn next_change(&self, index: &K) -> Option<&K> {
+        let pos = self.nodes.partition_point(|n| n.indexer <= *index);
+        self.nodes.get(pos).map(|n| &n.indexer)
+    }
+
+    /// Biggest index strictly smaller than the given one at which a state is stored
+    pub fn previous_change(&self, index: &K) -> Option<&K> {
+        let pos = self.nodes.partition_point(|n| n.indexer < *index);
+        pos.checked_sub(1).map(|p| &self.nodes[p].indexer)
+    }
+
+    /// Is there a state stored exactly at the given index ?
+    pub fn has_state(&self, index: &K) -> bool {
+        self.position(index).is_ok()
+    }
+
+    /// Every state stored in this tree, as (index, value), by increasing index
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.nodes.iter().map(|n| (&n.indexer, &n.value))
+    }
+
+    /// The states stored at or after the given index, as (index, value),
+    /// by increasing index
+    pub fn iter_from(&self, index: &K) -> impl Iterator<Item = (&K, &V)> {
+        let pos = self.nodes.partition_point(|n| n.indexer < *index);
+        self.nodes[pos..].iter().map(|n| (&n.indexer, &n.value))
+    }
+
+    /// The n-th state stored in this tree, as (index, value), by increasing index
+    pub fn nth(&self, n: usize) -> Option<(&K, &V)> {
+        self.nodes.get(n).map(|n| (&n.indexer, &n.value))
+    }
+
+    /// Number of states stored in this tree
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// A StateTree always contains at least its first state
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// Merges the runs of consecutive states holding the same value, keeping
+    /// the first state of each run.
+    /// Returns the number of removed states
+    pub fn compact(&mut self, same: impl Fn(&V, &V) -> bool) -> usize {
+        let len = self.nodes.len();
+        self.nodes
+            .dedup_by(|node, kept| same(&kept.value, &node.value));
+        len - self.nodes.len()
+    }
+
+    /// Moves each state to the later index given by `later`, if any, or drops
+    /// it when the next state is stored exactly at that later index.
+    /// No state may be stored strictly between an index and its later one.
+    /// Returns the number of removed states
+    pub fn merge_forward(&mut self, later: impl Fn(&K) -> Option<K>) -> usize {
+        let len = self.nodes.len();
+        let mut nodes = Vec::with_capacity(len);
+        let mut drained = std::mem::take(&mut self.nodes).into_iter().peekable();
+        while let Some(mut node) = drained.next() {
+            if let Some(to) = later(&node.indexer) {
+                if drained.peek().is_some_and(|next| next.indexer == to) {
+                    continue;
                 }
-                None => Some(&self.node.value),
-            },
+                node.indexer = to;
+            }
+            nodes.push(node);
         }
+        self.nodes = nodes;
+        len - self.nodes.len()
     }
 }
 
 /// Inner type, should never be constructed manually
 ///
 /// Represents a node on a `StateTree`
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 struct StateTreeNode<K, V> {
     indexer: K,
     value: V,
 }
 
-impl<K: Eq + Ord + Debug, V> StateTreeNode<K, V> {
-    /// Consumes the Node, and transforms it into a new `StateTree`,
-    /// the root of the newly created tree will have this node as its value
-    pub fn as_root(self) -> StateTree<K, V> {
-        StateTree::new(self.indexer, self.value)
-    }
-}
-
 #[cfg(test)]
 pub mod tests {
     use super::*;
@@ -145,28 +229,36 @@ pub mod tests {
     /// Pushing on a StateTree with different keys should never panic
     pub fn state_tree_push() {
         let mut tree: StateTree<usize, char> = StateTree::new(3, 'b');
-        tree.push(5, 'd');
-        tree.push(4, 'c');
-        tree.push(2, 'a');
-
-        assert!(tree.child_low.is_some());
-        assert!(tree.child_high.is_some());
+        tree.push(5, 'd').unwrap();
+        tree.push(4, 'c').unwrap();
+        tree.push(2, 'a').unwrap();
 
-        let tree_2 = tree.child_high.unwrap();
-        assert!(tree_2.child_low.is_some());
-        assert!(tree_2.child_high.is_none());
+        assert_eq!(tree.len(), 4);
+        let indexes: Vec<usize> = tree.nodes.iter().map(|n| n.indexer).collect();
+        assert_eq!(indexes, vec![2, 3, 4, 5]);
     }
 
     #[test]
-    #[should_panic]
     /// Pushing on a StateTree with a key that's already been placed should
-    /// panic.
-    /// TODO: API? Is it better to return a Result or to panic ?  
-    pub fn state_tree_push_panic() {
+    /// fail.
+    pub fn state_tree_push_duplicate() {
         let mut tree: StateTree<usize, char> = StateTree::new(2, 'c');
-        tree.push(0, 'a');
-        tree.push(1, 'b');
-        tree.push(2, 'c');
+        tree.push(0, 'a').unwrap();
+        tree.push(1, 'b').unwrap();
+        assert_eq!(tree.push(2, 'c'), Err(StorageError::IndexAlreadyUsed(2)));
+        assert_eq!(tree.push(0, 'd'), Err(StorageError::IndexAlreadyUsed(0)));
+        assert_eq!(tree.search(0), Some(&'a'));
+    }
+
+    #[test]
+    /// Overwriting replaces the state at an existing index, or inserts it
+    pub fn state_tree_overwrite() {
+        let mut tree: StateTree<usize, char> = StateTree::new(2, 'c');
+        assert_eq!(tree.overwrite(2, 'd'), Some('c'));
+        assert_eq!(tree.overwrite(0, 'a'), None);
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree.search(1), Some(&'a'));
+        assert_eq!(tree.search(2), Some(&'d'));
     }
 
     #[test]
@@ -174,9 +266,9 @@ pub mod tests {
     /// result and never panic
     pub fn state_tree_search() {
         let mut tree: StateTree<usize, char> = StateTree::new(20, 'c');
-        tree.push(5, 'a');
-        tree.push(10, 'b');
-        tree.push(25, 'd');
+        tree.push(5, 'a').unwrap();
+        tree.push(10, 'b').unwrap();
+        tree.push(25, 'd').unwrap();
 
         // Searching for undefined K (there is no K such as K < 0)
         (0..=4).for_each(|i| assert_eq!(tree.search(i), None));
@@ -203,6 +295,120 @@ pub mod tests {
                 _ => Some(&'d'),
             };
             assert_eq!(tree.search(i), result);
+            // Whatever the number of states known to be stored before
+            let before = tree.iter().filter(|(k, _)| **k <= i).count();
+            (0..=before).for_each(|known| assert_eq!(tree.search_from(known, &i), result));
         })
     }
+
+    #[test]
+    pub fn state_tree_has_state() {
+        let mut tree: StateTree<usize, char> = StateTree::new(2, 'a');
+        tree.push(5, 'b').unwrap();
+        assert!(tree.has_state(&2));
+        assert!(tree.has_state(&5));
+        assert!(!tree.has_state(&3));
+    }
+
+    #[test]
+    pub fn state_tree_changes() {
+        let mut tree = StateTree::new(3, 'c');
+        tree.push(1, 'a').unwrap();
+        tree.push(5, 'e').unwrap();
+
+        assert_eq!(tree.next_change(&0), Some(&1));
+        assert_eq!(tree.next_change(&1), Some(&3));
+        assert_eq!(tree.next_change(&4), Some(&5));
+        assert_eq!(tree.next_change(&5), None);
+        assert_eq!(tree.previous_change(&1), None);
+        assert_eq!(tree.previous_change(&3), Some(&1));
+        assert_eq!(tree.previous_change(&4), Some(&3));
+        assert_eq!(tree.previous_change(&100), Some(&5));
+    }
+
+    #[test]
+    pub fn state_tree_iter() {
+        let mut tree = StateTree::new(3, 'c');
+        tree.push(1, 'a').unwrap();
+        tree.push(5, 'e').unwrap();
+        assert_eq!(
+            tree.iter().collect::<Vec<_>>(),
+            vec![(&1, &'a'), (&3, &'c'), (&5, &'e')]
+        );
+        assert_eq!(
+            tree.iter_from(&2).collect::<Vec<_>>(),
+            vec![(&3, &'c'), (&5, &'e')]
+        );
+        assert_eq!(tree.iter_from(&6).count(), 0);
+        assert_eq!(tree.nth(1), Some((&3, &'c')));
+        assert_eq!(tree.nth(3), None);
+    }
+
+    #[test]
+    pub fn state_tree_compact() {
+        let mut tree = StateTree::new(0, 'a');
+        tree.push(1, 'a').unwrap();
+        tree.push(2, 'b').unwrap();
+        tree.push(3, 'b').unwrap();
+        tree.push(4, 'b').unwrap();
+        tree.push(5, 'a').unwrap();
+        assert_eq!(tree.compact(|a, b| a == b), 3);
+        assert_eq!(
+            tree.iter().collect::<Vec<_>>(),
+            vec![(&0, &'a'), (&2, &'b'), (&5, &'a')]
+        );
+        assert_eq!(tree.search(1), Some(&'a'));
+        assert_eq!(tree.search(4), Some(&'b'));
+    }
+
+    #[test]
+    pub fn state_tree_merge_forward() {
+        let mut tree = StateTree::new(1, 'a');
+        tree.push(2, 'b').unwrap();
+        tree.push(4, 'd').unwrap();
+        tree.push(6, 'f').unwrap();
+        // 1 merges into the state at 2, 4 moves to 5
+        let later = |i: &usize| match i {
+            1 => Some(2),
+            4 => Some(5),
+            _ => None,
+        };
+        assert_eq!(tree.merge_forward(later), 1);
+        assert_eq!(
+            tree.iter().collect::<Vec<_>>(),
+            vec![(&2, &'b'), (&5, &'d'), (&6, &'f')]
+        );
+    }
+
+    #[test]
+    /// Pushing a long run of increasing indexes should stay fast
+    /// and never overflow the stack
+    pub fn state_tree_long_run() {
+        let mut tree: StateTree<usize, usize> = StateTree::new(0, 0);
+        (1..100_000).for_each(|i| tree.push(i * 2, i).unwrap());
+
+        assert_eq!(tree.len(), 100_000);
+        assert_eq!(tree.search(0), Some(&0));
+        assert_eq!(tree.search(99_999), Some(&49_999));
+        assert_eq!(tree.search(1_000_000), Some(&99_999));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    pub fn state_tree_deserialize() {
+        let round_trip = |tree: &StateTree<usize, char>| {
+            let bytes = bincode::serialize(tree).unwrap();
+            bincode::deserialize::<StateTree<usize, char>>(&bytes)
+        };
+        let mut tree = StateTree::new(0, 'a');
+        tree.push(3, 'b').unwrap();
+        assert_eq!(round_trip(&tree).unwrap().search(4), Some(&'b'));
+
+        tree.nodes.swap(0, 1);
+        assert!(round_trip(&tree).is_err());
+        tree.nodes[0].indexer = 0;
+        assert!(round_trip(&tree).is_err());
+        tree.nodes.clear();
+        assert!(round_trip(&tree).is_err());
+    }
 }
@@ -0,0 +1,458 @@
+use crate::coords::{Bounds, Coords};
+use crate::state_tree::StateTree;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt::Debug;
+use std::mem::size_of;
+
+/// Smallest share of the positions of the bounds holding a value for which
+/// `AnyStorage::Auto` switches to a dense grid
+const DENSITY_THRESHOLD: f64 = 0.5;
+
+/// Biggest number of positions of a dense grid picked by `AnyStorage::Auto`
+const MAX_AUTO_AREA: u64 = 1 << 24;
+
+/// Where a DiffCache keeps the StateTree of each coordinates that got a value
+pub trait Storage<C: Coords, K: Ord + Eq + Debug, V> {
+    /// StateTree of the given coordinates, if they ever got a value
+    fn get(&self, coords: &C) -> Option<&StateTree<K, V>>;
+
+    /// StateTree of the given coordinates, if they ever got a value
+    fn get_mut(&mut self, coords: &C) -> Option<&mut StateTree<K, V>>;
+
+    /// Stores the StateTree of coordinates that did not have one yet
+    fn insert(&mut self, coords: C, tree: StateTree<K, V>);
+
+    /// Every coordinates that got a value with its StateTree, in no particular order
+    fn iter(&self) -> Box<dyn Iterator<Item = (C, &StateTree<K, V>)> + '_>;
+
+    /// Every coordinates that got a value with its mutable StateTree, in no particular order
+    fn iter_mut(&mut self) -> Box<dyn Iterator<Item = (C, &mut StateTree<K, V>)> + '_>;
+
+    /// Number of coordinates that got a value
+    fn len(&self) -> usize;
+
+    /// Did any coordinates get a value ?
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Called once all the states of an index are stored, with the bounds of
+    /// all the coordinates that got a value, so that the storage can reorganize.
+    /// Does nothing by default
+    fn reshape(&mut self, _bounds: &Bounds) {}
+
+    /// Rectangle in which the coordinates are laid out as a grid, if any
+    /// (cf. `Bounds::grid_position`). None by default
+    fn grid(&self) -> Option<Bounds> {
+        None
+    }
+
+    /// Estimated number of bytes used to hold the StateTrees, without the
+    /// states they store
+    fn memory_usage(&self) -> usize;
+}
+
+/// Estimated number of bytes used by the entries of a HashMap, counting its
+/// spare capacity and one control byte per entry
+pub(crate) fn hashed_memory_usage<C, T>(map: &HashMap<C, T>) -> usize {
+    map.capacity() * (size_of::<(C, T)>() + 1)
+}
+
+/// Storage of coordinates spread on the plane, hashing them
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SparseStorage<C: Coords, K: Ord + Eq + Debug, V> {
+    trees: HashMap<C, StateTree<K, V>>,
+}
+
+impl<C: Coords, K: Ord + Eq + Debug, V> Default for SparseStorage<C, K, V> {
+    fn default() -> Self {
+        SparseStorage {
+            trees: HashMap::new(),
+        }
+    }
+}
+
+impl<C: Coords, K: Ord + Eq + Debug, V> Storage<C, K, V> for SparseStorage<C, K, V> {
+    fn get(&self, coords: &C) -> Option<&StateTree<K, V>> {
+        self.trees.get(coords)
+    }
+
+    fn get_mut(&mut self, coords: &C) -> Option<&mut StateTree<K, V>> {
+        self.trees.get_mut(coords)
+    }
+
+    fn insert(&mut self, coords: C, tree: StateTree<K, V>) {
+        self.trees.insert(coords, tree);
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (C, &StateTree<K, V>)> + '_> {
+        Box::new(self.trees.iter().map(|(c, tree)| (c.clone(), tree)))
+    }
+
+    fn iter_mut(&mut self) -> Box<dyn Iterator<Item = (C, &mut StateTree<K, V>)> + '_> {
+        Box::new(self.trees.iter_mut().map(|(c, tree)| (c.clone(), tree)))
+    }
+
+    fn len(&self) -> usize {
+        self.trees.len()
+    }
+
+    fn memory_usage(&self) -> usize {
+        hashed_memory_usage(&self.trees)
+    }
+}
+
+/// Storage of coordinates packed in a rectangle, as a grid indexed by position.
+/// The coordinates of the grid are not stored but built back from their
+/// position (cf. `Coords::from_xy`).
+/// The coordinates outside of the rectangle are hashed, as in a SparseStorage.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "DenseParts<C, K, V>"))]
+pub struct DenseStorage<C: Coords, K: Ord + Eq + Debug, V> {
+    /// Rectangle covered by the grid
+    bounds: Bounds,
+    /// StateTree at each position of the rectangle, row by row
+    cells: Vec<Option<StateTree<K, V>>>,
+    /// Number of positions of the grid holding a StateTree
+    filled: usize,
+    /// StateTrees of the coordinates outside of the rectangle
+    overflow: HashMap<C, StateTree<K, V>>,
+}
+
+impl<C: Coords, K: Ord + Eq + Debug, V> DenseStorage<C, K, V> {
+    /// Creates a new instance of a DenseStorage, whose grid covers the given bounds.
+    /// Panics if the number of positions of the bounds does not fit in a usize
+    pub fn new(bounds: Bounds) -> Self {
+        let area = bounds
+            .area()
+            .and_then(|area| usize::try_from(area).ok())
+            .expect("Bounds too big for a dense grid");
+        let mut cells = Vec::new();
+        cells.resize_with(area, || None);
+        DenseStorage {
+            bounds,
+            cells,
+            filled: 0,
+            overflow: HashMap::new(),
+        }
+    }
+
+    /// Rectangle covered by the grid
+    pub fn bounds(&self) -> Bounds {
+        self.bounds
+    }
+}
+
+/// Fields of a deserialized DenseStorage, before checking that they agree
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct DenseParts<C: Coords, K: Ord + Eq + Debug, V> {
+    bounds: Bounds,
+    cells: Vec<Option<StateTree<K, V>>>,
+    filled: usize,
+    overflow: HashMap<C, StateTree<K, V>>,
+}
+
+#[cfg(feature = "serde")]
+impl<C: Coords, K: Ord + Eq + Debug, V> TryFrom<DenseParts<C, K, V>> for DenseStorage<C, K, V> {
+    type Error = &'static str;
+
+    fn try_from(parts: DenseParts<C, K, V>) -> Result<Self, Self::Error> {
+        let bounds = parts.bounds;
+        if bounds.min.0 > bounds.max.0 || bounds.min.1 > bounds.max.1 {
+            return Err("DenseStorage with empty bounds");
+        }
+        let area = bounds.area().and_then(|area| usize::try_from(area).ok());
+        if area != Some(parts.cells.len()) {
+            return Err("DenseStorage whose grid does not cover its bounds");
+        }
+        if parts.cells.iter().filter(|cell| cell.is_some()).count() != parts.filled {
+            return Err("DenseStorage with a wrong number of positions filled");
+        }
+        if parts
+            .overflow
+            .keys()
+            .any(|c| bounds.grid_position(c).is_some())
+        {
+            return Err("DenseStorage with coordinates of its grid out of it");
+        }
+        Ok(DenseStorage {
+            bounds,
+            cells: parts.cells,
+            filled: parts.filled,
+            overflow: parts.overflow,
+        })
+    }
+}
+
+impl<C: Coords, K: Ord + Eq + Debug, V> Storage<C, K, V> for DenseStorage<C, K, V> {
+    fn get(&self, coords: &C) -> Option<&StateTree<K, V>> {
+        match self.bounds.grid_position(coords) {
+            Some(p) => self.cells[p].as_ref(),
+            None => self.overflow.get(coords),
+        }
+    }
+
+    fn get_mut(&mut self, coords: &C) -> Option<&mut StateTree<K, V>> {
+        match self.bounds.grid_position(coords) {
+            Some(p) => self.cells[p].as_mut(),
+            None => self.overflow.get_mut(coords),
+        }
+    }
+
+    fn insert(&mut self, coords: C, tree: StateTree<K, V>) {
+        match self.bounds.grid_position(&coords) {
+            Some(p) => {
+                if self.cells[p].replace(tree).is_none() {
+                    self.filled += 1;
+                }
+            }
+            None => {
+                self.overflow.insert(coords, tree);
+            }
+        }
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (C, &StateTree<K, V>)> + '_> {
+        let bounds = self.bounds;
+        let cells = self.cells.iter().enumerate().filter_map(move |(p, cell)| {
+            let tree = cell.as_ref()?;
+            Some((bounds.grid_coords(p)?, tree))
+        });
+        let overflow = self.overflow.iter().map(|(c, tree)| (c.clone(), tree));
+        Box::new(cells.chain(overflow))
+    }
+
+    fn iter_mut(&mut self) -> Box<dyn Iterator<Item = (C, &mut StateTree<K, V>)> + '_> {
+        let bounds = self.bounds;
+        let cells = self
+            .cells
+            .iter_mut()
+            .enumerate()
+            .filter_map(move |(p, cell)| {
+                let tree = cell.as_mut()?;
+                Some((bounds.grid_coords(p)?, tree))
+            });
+        let overflow = self.overflow.iter_mut().map(|(c, tree)| (c.clone(), tree));
+        Box::new(cells.chain(overflow))
+    }
+
+    fn len(&self) -> usize {
+        self.filled + self.overflow.len()
+    }
+
+    fn grid(&self) -> Option<Bounds> {
+        Some(self.bounds)
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.cells.capacity() * size_of::<Option<StateTree<K, V>>>()
+            + hashed_memory_usage(&self.overflow)
+    }
+}
+
+/// Storage used by default by a DiffCache, either sparse or dense
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AnyStorage<C: Coords, K: Ord + Eq + Debug, V> {
+    /// Sparse, until most of the positions in the bounds of the coordinates
+    /// hold a value: it then switches to a dense grid covering these bounds
+    Auto(SparseStorage<C, K, V>),
+    /// Always sparse
+    Sparse(SparseStorage<C, K, V>),
+    /// Dense in a given rectangle
+    Dense(DenseStorage<C, K, V>),
+}
+
+impl<C: Coords, K: Ord + Eq + Debug, V> AnyStorage<C, K, V> {
+    /// A storage that stays sparse
+    pub fn sparse() -> Self {
+        AnyStorage::Sparse(SparseStorage::default())
+    }
+
+    /// A storage using a dense grid for the coordinates inside the given bounds
+    pub fn dense(bounds: Bounds) -> Self {
+        AnyStorage::Dense(DenseStorage::new(bounds))
+    }
+
+    /// Is a dense grid being used ?
+    pub fn is_dense(&self) -> bool {
+        match self {
+            AnyStorage::Dense(_) => true,
+            AnyStorage::Auto(_) | AnyStorage::Sparse(_) => false,
+        }
+    }
+
+    fn inner(&self) -> &dyn Storage<C, K, V> {
+        match self {
+            AnyStorage::Auto(sparse) | AnyStorage::Sparse(sparse) => sparse,
+            AnyStorage::Dense(dense) => dense,
+        }
+    }
+
+    fn inner_mut(&mut self) -> &mut dyn Storage<C, K, V> {
+        match self {
+            AnyStorage::Auto(sparse) | AnyStorage::Sparse(sparse) => sparse,
+            AnyStorage::Dense(dense) => dense,
+        }
+    }
+}
+
+impl<C: Coords, K: Ord + Eq + Debug, V> Default for AnyStorage<C, K, V> {
+    fn default() -> Self {
+        AnyStorage::Auto(SparseStorage::default())
+    }
+}
+
+impl<C: Coords, K: Ord + Eq + Debug, V> Storage<C, K, V> for AnyStorage<C, K, V> {
+    fn get(&self, coords: &C) -> Option<&StateTree<K, V>> {
+        self.inner().get(coords)
+    }
+
+    fn get_mut(&mut self, coords: &C) -> Option<&mut StateTree<K, V>> {
+        self.inner_mut().get_mut(coords)
+    }
+
+    fn insert(&mut self, coords: C, tree: StateTree<K, V>) {
+        self.inner_mut().insert(coords, tree)
+    }
+
+    fn iter(&self) -> Box<dyn Iterator<Item = (C, &StateTree<K, V>)> + '_> {
+        self.inner().iter()
+    }
+
+    fn iter_mut(&mut self) -> Box<dyn Iterator<Item = (C, &mut StateTree<K, V>)> + '_> {
+        self.inner_mut().iter_mut()
+    }
+
+    fn len(&self) -> usize {
+        self.inner().len()
+    }
+
+    fn grid(&self) -> Option<Bounds> {
+        self.inner().grid()
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.inner().memory_usage()
+    }
+
+    /// Switches from sparse to dense once dense enough, when picked automatically
+    fn reshape(&mut self, bounds: &Bounds) {
+        let sparse = match self {
+            AnyStorage::Auto(sparse) => sparse,
+            AnyStorage::Sparse(_) | AnyStorage::Dense(_) => return,
+        };
+        // Stays sparse when the area of the bounds overflows
+        let area = bounds.area().unwrap_or(u64::MAX);
+        if area > MAX_AUTO_AREA || (sparse.len() as f64) < area as f64 * DENSITY_THRESHOLD {
+            return;
+        }
+        let mut dense = DenseStorage::new(*bounds);
+        sparse
+            .trees
+            .drain()
+            .for_each(|(c, tree)| dense.insert(c, tree));
+        *self = AnyStorage::Dense(dense);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn dense_storage() {
+        let mut bounds = Bounds::new(-1, -1);
+        bounds.include(1, 1);
+        let mut storage: DenseStorage<(i32, i32), usize, char> = DenseStorage::new(bounds);
+        storage.insert((-1, 0), StateTree::new(0, 'a'));
+        storage.insert((1, 1), StateTree::new(0, 'b'));
+        // Outside of the grid
+        storage.insert((5, 0), StateTree::new(1, 'c'));
+        storage.get_mut(&(1, 1)).unwrap().push(2, 'd').unwrap();
+
+        assert_eq!(storage.len(), 3);
+        assert_eq!(storage.get(&(-1, 0)).unwrap().search(0), Some(&'a'));
+        assert_eq!(storage.get(&(1, 1)).unwrap().search(2), Some(&'d'));
+        assert_eq!(storage.get(&(5, 0)).unwrap().search(1), Some(&'c'));
+        assert!(storage.get(&(0, 0)).is_none());
+        assert!(storage.get(&(-5, 0)).is_none());
+
+        let mut coords: Vec<(i32, i32)> = storage.iter().map(|(c, _)| c).collect();
+        coords.sort();
+        assert_eq!(coords, vec![(-1, 0), (1, 1), (5, 0)]);
+        assert_eq!(storage.grid(), Some(bounds));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    pub fn dense_storage_deserialize() {
+        let round_trip = |storage: &DenseStorage<(i32, i32), usize, char>| {
+            let bytes = bincode::serialize(storage).unwrap();
+            bincode::deserialize::<DenseStorage<(i32, i32), usize, char>>(&bytes)
+        };
+        let mut bounds = Bounds::new(0, 0);
+        bounds.include(1, 1);
+        let mut storage = DenseStorage::new(bounds);
+        storage.insert((1, 0), StateTree::new(0, 'a'));
+        storage.insert((5, 5), StateTree::new(0, 'b'));
+        assert_eq!(round_trip(&storage).unwrap().len(), 2);
+
+        let mut invalid = storage.clone();
+        invalid.filled = 2;
+        assert!(round_trip(&invalid).is_err());
+        let mut invalid = storage.clone();
+        invalid.cells.pop();
+        assert!(round_trip(&invalid).is_err());
+        let mut invalid = storage.clone();
+        invalid.bounds = Bounds::new(1, 1);
+        invalid.bounds.include(0, 0);
+        std::mem::swap(&mut invalid.bounds.min, &mut invalid.bounds.max);
+        assert!(round_trip(&invalid).is_err());
+        let mut invalid = storage;
+        invalid.overflow.insert((0, 0), StateTree::new(1, 'c'));
+        assert!(round_trip(&invalid).is_err());
+    }
+
+    #[test]
+    pub fn any_storage_auto() {
+        let mut storage: AnyStorage<(i32, i32), usize, char> = AnyStorage::default();
+        storage.insert((0, 0), StateTree::new(0, 'a'));
+        storage.insert((9, 9), StateTree::new(0, 'b'));
+        let mut bounds = Bounds::new(0, 0);
+        bounds.include(9, 9);
+
+        // 2 positions out of 100
+        storage.reshape(&bounds);
+        assert!(!storage.is_dense());
+
+        let rows = |ys: std::ops::Range<i32>| ys.flat_map(|y| (0..10).map(move |x| (x, y)));
+        rows(1..4).for_each(|c| storage.insert(c, StateTree::new(1, 'c')));
+        storage.reshape(&bounds);
+        assert!(!storage.is_dense());
+        rows(4..6).for_each(|c| storage.insert(c, StateTree::new(1, 'c')));
+        storage.reshape(&bounds);
+        assert!(storage.is_dense());
+        assert_eq!(storage.len(), 52);
+        assert_eq!(storage.get(&(9, 9)).unwrap().search(0), Some(&'b'));
+
+        // Bounds whose area overflows stay sparse
+        let mut storage: AnyStorage<(i64, i64), usize, char> = AnyStorage::default();
+        storage.insert((i64::MIN, 0), StateTree::new(0, 'a'));
+        storage.insert((i64::MAX, 0), StateTree::new(0, 'b'));
+        let mut bounds = Bounds::new(i64::MIN, 0);
+        bounds.include(i64::MAX, 0);
+        storage.reshape(&bounds);
+        assert!(!storage.is_dense());
+
+        // Sparse storages never switch
+        let mut storage: AnyStorage<(i32, i32), usize, char> = AnyStorage::sparse();
+        storage.insert((0, 0), StateTree::new(0, 'a'));
+        storage.reshape(&Bounds::new(0, 0));
+        assert!(!storage.is_dense());
+    }
+}
@@ -1,3 +1,53 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::time::Duration;
+
+/// Fastest playback speed, in frames per second
+const MAX_FPS: u32 = 1000;
+
+/// Direction in which the playback moves through time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Direction {
+    Forward,
+    Backward,
+}
+
+/// State of the automatic playback of the time frames
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Playback {
+    /// Is the playback currently running ?
+    pub playing: bool,
+    /// Speed of the playback, in frames per second
+    pub fps: u32,
+    /// Direction of the playback
+    pub direction: Direction,
+}
+
+impl Default for Playback {
+    fn default() -> Self {
+        Playback {
+            playing: false,
+            fps: 10,
+            direction: Direction::Forward,
+        }
+    }
+}
+
+/// State of the background generation of the time frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Generation {
+    /// Frames are still being produced
+    Running,
+    /// All the frames have been produced, in the given duration
+    Finished(Duration),
+    /// The user's fn panicked after the given duration, no more frames will come
+    Failed(Duration),
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TimeIndex {
     /// Minimum time index available
     pub min: usize,
@@ -5,17 +55,92 @@ pub struct TimeIndex {
     pub max: usize,
     /// Current index
     pub current: usize,
+    /// Automatic playback state
+    pub playback: Playback,
+    /// Should the current index follow the maximum index as it grows ?
+    pub follow: bool,
+    /// Background generation state
+    pub generation: Generation,
+    /// Indexes at which a breakpoint was hit, with the name of the breakpoint
+    pub breaks: BTreeMap<usize, String>,
+    /// Ranges of indexes whose frames are approximated, since they were
+    /// decimated to keep to the memory budget
+    pub decimated: Vec<Range<usize>>,
 }
 
 impl TimeIndex {
     /// Creates a new instance of the TimeIndex
     pub fn new(min: usize, max: usize, current: usize) -> Self {
-        TimeIndex { min, max, current }
+        TimeIndex {
+            min,
+            max,
+            current,
+            playback: Playback::default(),
+            follow: false,
+            generation: Generation::Running,
+            breaks: BTreeMap::new(),
+            decimated: Vec::new(),
+        }
     }
 
-    /// Adds a maximum index
+    /// Adds a maximum index.
+    /// In follow mode, the current index moves to the new maximum, and stops
+    /// there if a breakpoint was hit at this index (cf. `hit_breakpoint`).
     pub fn add_max(&mut self) {
         self.max += 1;
+        if self.follow {
+            self.current = self.max;
+            if self.breaks.contains_key(&self.max) {
+                self.stop_following();
+            }
+        }
+    }
+
+    /// Enables or disables the follow mode.
+    /// Enabling it jumps to the maximum index right away.
+    pub fn toggle_follow(&mut self) {
+        self.follow = !self.follow;
+        if self.follow {
+            self.current = self.max;
+        }
+    }
+
+    /// Records that a breakpoint was hit at the given index, whose frame may
+    /// not be stored yet.
+    /// In follow mode, the current index stops there and the follow mode is
+    /// disabled, once the index is available.
+    pub fn hit_breakpoint(&mut self, index: usize, name: String) {
+        self.breaks.entry(index).or_insert(name);
+        if self.follow && index <= self.max {
+            self.current = index;
+            self.stop_following();
+        }
+    }
+
+    /// Disables the follow mode and the playback at the current index
+    fn stop_following(&mut self) {
+        self.follow = false;
+        self.playback.playing = false;
+    }
+
+    /// Name of the breakpoint hit at the current index, if any
+    pub fn current_break(&self) -> Option<&str> {
+        self.breaks.get(&self.current).map(String::as_str)
+    }
+
+    /// Is the frame at the given index approximated, since it was decimated ?
+    pub fn is_decimated(&self, index: usize) -> bool {
+        self.decimated.iter().any(|r| r.contains(&index))
+    }
+
+    /// Marks the generation as finished, after the given duration
+    pub fn finish_generation(&mut self, duration: Duration) {
+        self.generation = Generation::Finished(duration);
+    }
+
+    /// Marks the generation as failed, after the given duration
+    pub fn fail_generation(&mut self, duration: Duration) {
+        self.generation = Generation::Failed(duration);
     }
 
     /// Sets the current time frame, checking the min and max bounds.
@@ -45,4 +170,157 @@ impl TimeIndex {
             _ => self.current,
         }
     }
+
+    /// Starts or pauses the playback
+    pub fn toggle_play(&mut self) {
+        self.playback.playing = !self.playback.playing;
+    }
+
+    /// Doubles the playback speed
+    pub fn speed_up(&mut self) {
+        self.playback.fps = (self.playback.fps * 2).min(MAX_FPS);
+    }
+
+    /// Halves the playback speed
+    pub fn slow_down(&mut self) {
+        self.playback.fps = (self.playback.fps / 2).max(1);
+    }
+
+    /// Reverses the direction of the playback
+    pub fn reverse(&mut self) {
+        self.playback.direction = match self.playback.direction {
+            Direction::Forward => Direction::Backward,
+            Direction::Backward => Direction::Forward,
+        }
+    }
+
+    /// Moves the current time frame one step in the playback direction,
+    /// if the playback is running.
+    /// The playback is paused when reaching the min or max bound, or an index
+    /// at which a breakpoint was hit.
+    /// Returns true if the current time frame changed
+    pub fn tick(&mut self) -> bool {
+        if !self.playback.playing {
+            return false;
+        }
+        let previous = self.current;
+        match self.playback.direction {
+            Direction::Forward => self.forward(),
+            Direction::Backward => self.backward(),
+        }
+        if previous == self.current || self.breaks.contains_key(&self.current) {
+            self.playback.playing = false;
+        }
+        previous != self.current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn time_index_tick() {
+        let mut time_index = TimeIndex::new(0, 2, 0);
+
+        // Nothing happens while paused
+        assert!(!time_index.tick());
+        assert_eq!(time_index.current, 0);
+
+        time_index.toggle_play();
+        assert!(time_index.tick());
+        assert!(time_index.tick());
+        assert_eq!(time_index.current, 2);
+
+        // Reaching the end pauses the playback
+        assert!(!time_index.tick());
+        assert!(!time_index.playback.playing);
+
+        time_index.reverse();
+        time_index.toggle_play();
+        assert!(time_index.tick());
+        assert_eq!(time_index.current, 1);
+    }
+
+    #[test]
+    pub fn time_index_follow() {
+        let mut time_index = TimeIndex::new(0, 3, 1);
+        time_index.add_max();
+        assert_eq!(time_index.current, 1);
+
+        time_index.toggle_follow();
+        assert_eq!(time_index.current, 4);
+        time_index.add_max();
+        time_index.add_max();
+        assert_eq!(time_index.current, 6);
+
+        time_index.toggle_follow();
+        time_index.add_max();
+        assert_eq!(time_index.current, 6);
+    }
+
+    #[test]
+    pub fn time_index_breakpoint() {
+        let mut time_index = TimeIndex::new(0, 2, 0);
+        time_index.toggle_follow();
+        time_index.add_max();
+        time_index.hit_breakpoint(3, "first".into());
+        time_index.add_max();
+        time_index.hit_breakpoint(4, "second".into());
+
+        // The follow mode stops at the first hit
+        assert!(!time_index.follow);
+        assert_eq!(time_index.current, 3);
+        assert_eq!(time_index.current_break(), Some("first"));
+
+        // The playback stops at the next hit
+        time_index.set_current(0);
+        time_index.toggle_play();
+        assert!(time_index.tick());
+        assert!(time_index.tick());
+        assert!(time_index.tick());
+        assert!(!time_index.playback.playing);
+        assert_eq!(time_index.current, 3);
+        time_index.toggle_play();
+        assert!(time_index.tick());
+        assert!(!time_index.playback.playing);
+        assert_eq!(time_index.current_break(), Some("second"));
+    }
+
+    #[test]
+    pub fn time_index_breakpoint_ahead() {
+        let mut time_index = TimeIndex::new(0, 2, 0);
+        time_index.toggle_follow();
+
+        // The breakpoint is hit before its frame is stored: following goes on until then
+        time_index.hit_breakpoint(4, "ahead".into());
+        assert!(time_index.follow);
+        time_index.add_max();
+        assert_eq!(time_index.current, 3);
+        time_index.add_max();
+        assert!(!time_index.follow);
+        time_index.add_max();
+        assert_eq!(time_index.current, 4);
+        assert_eq!(time_index.current_break(), Some("ahead"));
+    }
+
+    #[test]
+    pub fn time_index_decimated() {
+        let mut time_index = TimeIndex::new(0, 20, 0);
+        time_index.decimated = vec![2..5, 8..9];
+        let decimated: Vec<usize> = (0..20).filter(|i| time_index.is_decimated(*i)).collect();
+        assert_eq!(decimated, vec![2, 3, 4, 8]);
+    }
+
+    #[test]
+    pub fn time_index_speed() {
+        let mut time_index = TimeIndex::new(0, 0, 0);
+        time_index.playback.fps = 2;
+        time_index.slow_down();
+        time_index.slow_down();
+        assert_eq!(time_index.playback.fps, 1);
+
+        (0..20).for_each(|_| time_index.speed_up());
+        assert_eq!(time_index.playback.fps, MAX_FPS);
+    }
 }
@@ -0,0 +1,237 @@
+use crate::cell::Cell;
+
+/// How the FrameView summarizes a block of coordinates in a single
+/// terminal character, when zoomed out.
+///
+/// Coordinates are either lit (holding something to show) or not.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Aggregation {
+    /// The most common lit Cell of the block
+    #[default]
+    MostCommon,
+    /// A character of a density ramp, denser as more of the block is lit
+    Density,
+    /// Half-block characters, splitting the block in 1x2 subpixels
+    HalfBlock,
+    /// Braille characters, splitting the block in 2x4 subpixels
+    Braille,
+}
+
+/// Characters used by the Density aggregation, from empty to full
+const DENSITY_RAMP: [char; 10] = [' ', '.', ':', '-', '=', '+', '*', '#', '%', '@'];
+
+/// Characters used by the HalfBlock aggregation, indexed by (top lit, bottom lit)
+const HALF_BLOCKS: [char; 4] = [' ', '▀', '▄', '█'];
+
+/// Bits of the braille dots, indexed by [row][column]
+const BRAILLE_DOTS: [[u32; 2]; 4] = [[0x01, 0x08], [0x02, 0x10], [0x04, 0x20], [0x40, 0x80]];
+
+impl Aggregation {
+    /// Number of subpixels (columns, rows) drawn in a terminal character
+    pub fn subpixels(self) -> (usize, usize) {
+        match self {
+            Aggregation::MostCommon | Aggregation::Density => (1, 1),
+            Aggregation::HalfBlock => (1, 2),
+            Aggregation::Braille => (2, 4),
+        }
+    }
+
+    /// The aggregation following this one, cycling through all of them
+    pub fn next(self) -> Self {
+        match self {
+            Aggregation::MostCommon => Aggregation::Density,
+            Aggregation::Density => Aggregation::HalfBlock,
+            Aggregation::HalfBlock => Aggregation::Braille,
+            Aggregation::Braille => Aggregation::MostCommon,
+        }
+    }
+
+    /// Summarizes a block of coordinates, given as (Cell, lit) row by row,
+    /// `width` coordinates per row, in the Cell to print
+    pub fn aggregate(self, block: &[(Cell, bool)], width: usize) -> Cell {
+        let width = width.max(1);
+        let cells: Vec<((usize, usize), Cell, bool)> = block
+            .iter()
+            .enumerate()
+            .map(|(i, (cell, lit))| ((i % width, i / width), *cell, *lit))
+            .collect();
+        let unset = (Cell::default(), false);
+        self.aggregate_sparse(&cells, (width, block.len() / width), unset)
+    }
+
+    /// Summarizes a block of `size` (width, height) coordinates in the Cell to
+    /// print, given only some of them as ((X, Y) in the block, Cell, lit):
+    /// all the others show the `unset` (Cell, lit).
+    /// Big blocks are summarized without going through each of their coordinates
+    pub fn aggregate_sparse(
+        self,
+        cells: &[((usize, usize), Cell, bool)],
+        size: (usize, usize),
+        unset: (Cell, bool),
+    ) -> Cell {
+        let size = (size.0.max(1), size.1.max(1));
+        let area = size.0 as u128 * size.1 as u128;
+        let unset_count = area.saturating_sub(cells.len() as u128);
+        // (Cell, lit, number of coordinates)
+        let counted = || {
+            cells
+                .iter()
+                .map(|(_, cell, lit)| (*cell, *lit, 1))
+                .chain(Some((unset.0, unset.1, unset_count)).filter(|(_, _, n)| *n > 0))
+        };
+        let dominant = most_common(counted().filter(|(_, lit, _)| *lit));
+        let glyph = match self {
+            Aggregation::MostCommon => {
+                return dominant
+                    .or_else(|| most_common(counted()))
+                    .unwrap_or_default()
+            }
+            Aggregation::Density => {
+                let lit: u128 = counted()
+                    .filter(|(_, lit, _)| *lit)
+                    .map(|(_, _, n)| n)
+                    .sum();
+                let last = DENSITY_RAMP.len() - 1;
+                // Rounds up, so that a block with anything lit is never blank
+                DENSITY_RAMP[(lit * last as u128).div_ceil(area) as usize]
+            }
+            Aggregation::HalfBlock => {
+                let lit = subpixels_lit(cells, size, self.subpixels(), unset_count > 0 && unset.1);
+                HALF_BLOCKS[lit[0] as usize | (lit[1] as usize) << 1]
+            }
+            Aggregation::Braille => {
+                let lit = subpixels_lit(cells, size, self.subpixels(), unset_count > 0 && unset.1);
+                let dots = (0..8)
+                    .filter(|i| lit[*i])
+                    .map(|i| BRAILLE_DOTS[i / 2][i % 2])
+                    .sum::<u32>();
+                match dots {
+                    // Blank braille characters are not always rendered as blank
+                    0 => ' ',
+                    dots => std::char::from_u32(0x2800 + dots).unwrap_or(' '),
+                }
+            }
+        };
+        let mut cell = Cell::new(glyph);
+        cell.fg = dominant.and_then(|c| c.fg);
+        cell
+    }
+}
+
+/// Most common Cell among the given (Cell, lit, number of coordinates), the
+/// first one seen winning ties
+fn most_common(cells: impl Iterator<Item = (Cell, bool, u128)>) -> Option<Cell> {
+    let mut counts: Vec<(Cell, u128)> = Vec::new();
+    cells.for_each(
+        |(cell, _, n)| match counts.iter_mut().find(|(c, _)| *c == cell) {
+            Some((_, count)) => *count += n,
+            None => counts.push((cell, n)),
+        },
+    );
+    counts
+        .iter()
+        .rev()
+        .max_by_key(|(_, count)| *count)
+        .map(|(cell, _)| *cell)
+}
+
+/// Splits a block of `size` coordinates in the given number of (columns, rows)
+/// of subpixels, and tells whether anything is lit in each of them, row by row.
+/// When the coordinates not given are lit, every subpixel is
+fn subpixels_lit(
+    cells: &[((usize, usize), Cell, bool)],
+    size: (usize, usize),
+    subpixels: (usize, usize),
+    unset_lit: bool,
+) -> Vec<bool> {
+    let (columns, rows) = subpixels;
+    let mut lit = vec![unset_lit; columns * rows];
+    cells
+        .iter()
+        .filter(|(_, _, l)| *l)
+        .for_each(|((x, y), _, _)| {
+            let column = (*x as u128 * columns as u128 / size.0 as u128) as usize;
+            let row = (*y as u128 * rows as u128 / size.1 as u128) as usize;
+            lit[row * columns + column] = true;
+        });
+    lit
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cursive::theme::{BaseColor, Color};
+
+    /// Builds a block from glyphs, '.' being the only unlit one
+    fn block(glyphs: &str) -> Vec<(Cell, bool)> {
+        glyphs.chars().map(|g| (Cell::new(g), g != '.')).collect()
+    }
+
+    #[test]
+    pub fn aggregation_most_common() {
+        let aggregation = Aggregation::MostCommon;
+        assert_eq!(aggregation.aggregate(&block("..a.bb.a"), 4).glyph, 'a');
+        assert_eq!(aggregation.aggregate(&block("..a.bbba"), 4).glyph, 'b');
+        assert_eq!(aggregation.aggregate(&block("...."), 2).glyph, '.');
+        assert_eq!(aggregation.aggregate(&block("#"), 1).glyph, '#');
+    }
+
+    #[test]
+    pub fn aggregation_density() {
+        let aggregation = Aggregation::Density;
+        assert_eq!(aggregation.aggregate(&block("...."), 2).glyph, ' ');
+        assert_eq!(aggregation.aggregate(&block("#..."), 2).glyph, '-');
+        assert_eq!(aggregation.aggregate(&block("####"), 2).glyph, '@');
+        assert_eq!(aggregation.aggregate(&block("#"), 1).glyph, '@');
+        let sparse = format!("#{}", ".".repeat(99));
+        assert_eq!(aggregation.aggregate(&block(&sparse), 10).glyph, '.');
+    }
+
+    #[test]
+    pub fn aggregation_half_block() {
+        let aggregation = Aggregation::HalfBlock;
+        assert_eq!(aggregation.aggregate(&block("#."), 1).glyph, '▀');
+        assert_eq!(aggregation.aggregate(&block("..#."), 2).glyph, '▄');
+        assert_eq!(aggregation.aggregate(&block(".#.#"), 2).glyph, '█');
+        assert_eq!(aggregation.aggregate(&block("...."), 2).glyph, ' ');
+    }
+
+    #[test]
+    pub fn aggregation_braille() {
+        let aggregation = Aggregation::Braille;
+        assert_eq!(aggregation.aggregate(&block("........"), 2).glyph, ' ');
+        assert_eq!(aggregation.aggregate(&block("#......."), 2).glyph, '⠁');
+        assert_eq!(aggregation.aggregate(&block(".#.....#"), 2).glyph, '⢈');
+        assert_eq!(aggregation.aggregate(&block("########"), 2).glyph, '⣿');
+        // 4x8 coordinates, each dot covering 2x2 of them
+        let mut glyphs = ".".repeat(32);
+        glyphs.replace_range(31..32, "#");
+        assert_eq!(aggregation.aggregate(&block(&glyphs), 4).glyph, '⢀');
+    }
+
+    #[test]
+    pub fn aggregation_sparse() {
+        // A 1000x1000 block, with a single lit coordinate near its bottom-right corner
+        let cells = vec![((999, 998), Cell::new('#'), true)];
+        let size = (1000, 1000);
+        let unset = (Cell::new('.'), false);
+        let aggregate =
+            |aggregation: Aggregation| aggregation.aggregate_sparse(&cells, size, unset).glyph;
+        assert_eq!(aggregate(Aggregation::MostCommon), '#');
+        assert_eq!(aggregate(Aggregation::Density), '.');
+        assert_eq!(aggregate(Aggregation::HalfBlock), '▄');
+        assert_eq!(aggregate(Aggregation::Braille), '⢀');
+        // Without anything lit, the most common Cell is the unset one
+        let unlit = vec![((0, 0), Cell::new('#'), false)];
+        let cell = Aggregation::MostCommon.aggregate_sparse(&unlit, size, unset);
+        assert_eq!(cell.glyph, '.');
+    }
+
+    #[test]
+    pub fn aggregation_keeps_colour() {
+        let red = Cell::new('#').fg(Color::Dark(BaseColor::Red));
+        let block = vec![(red, true), (Cell::new('.'), false)];
+        let cell = Aggregation::HalfBlock.aggregate(&block, 1);
+        assert_eq!((cell.glyph, cell.fg), ('▀', red.fg));
+    }
+}
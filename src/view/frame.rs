@@ -1,44 +1,365 @@
-use crate::diff_cache::DiffCache;
-use crate::time_index::TimeIndex;
+use crate::cell::{Cell, Displayable};
+use crate::coords::{Bounds, Coords};
+use crate::diff_cache::Frame;
+use crate::shared_cache::SharedDiffCache;
+use crate::time_index::{Direction as TimeDirection, TimeIndex};
+use crate::view::aggregation::Aggregation;
+use crate::view::inspector::Inspector;
 use cursive::direction::Direction;
-use cursive::event::{Event, EventResult, Key};
+use cursive::event::{Event, EventResult, Key, MouseButton, MouseEvent};
+use cursive::theme::Effect;
 use cursive::view::View;
 use cursive::{Printer, Vec2};
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt::Debug;
 use std::sync::{Arc, Mutex};
 
+/// Tells whether a value is lit, i.e. drawn as a pixel by the aggregations
+/// packing several coordinates in a character
+pub type LitPredicate<V> = Box<dyn Fn(&V) -> bool>;
+
+/// Coordinates of a block that got a value, as ((X, Y) in the block, Cell, lit)
+type StoredBlock = Vec<((usize, usize), Cell, bool)>;
+
 /// Represents a Viewport that cargo-aoc-viz will use
 /// to visualize a DiffTree
-pub struct FrameView {
+pub struct FrameView<C: Coords, V> {
     /// Center (X/Y coordinates) of the viewport
-    origin: (i32, i32),
+    origin: (i64, i64),
     /// Size of the viewport
     size: Vec2,
     /// The TimeIndex to use
     time_index: Arc<Mutex<TimeIndex>>,
     /// Data source (an atomic ref to the DiffCache that this view is displaying)
-    target: Arc<Mutex<DiffCache<(i32, i32), usize, char>>>,
+    target: SharedDiffCache<C, V>,
+    /// Last position of the mouse while the left button is held, if it is
+    drag: Option<Vec2>,
+    /// Whether the mouse moved since the left button was pressed
+    dragged: bool,
+    /// Logical X/Y coordinates of the cursor, if it is shown
+    cursor: Option<(i64, i64)>,
+    /// Should the viewport be centered on the changes of the current frame ?
+    track_changes: bool,
+    /// Should the viewport fit the content once there is some, if none of it
+    /// is visible ?
+    auto_fit: bool,
+    /// Width and height of the block of coordinates drawn by each subpixel
+    zoom: usize,
+    /// How the coordinates of a block are summarized when zoomed out
+    aggregation: Aggregation,
+    /// Tells the lit values apart. If unset, values shown differently from
+    /// the default value are lit
+    is_lit: Option<LitPredicate<V>>,
 }
 
-impl FrameView {
+impl<C: Coords, V: Displayable> FrameView<C, V> {
     /// Creates a new instance of the FrameView
-    pub fn new(
-        target: Arc<Mutex<DiffCache<(i32, i32), usize, char>>>,
-        time_index: Arc<Mutex<TimeIndex>>,
-    ) -> Self {
+    pub fn new(target: SharedDiffCache<C, V>, time_index: Arc<Mutex<TimeIndex>>) -> Self {
         FrameView {
             origin: (0, 0),
             size: Vec2::new(0, 0),
             time_index,
             target,
+            drag: None,
+            dragged: false,
+            cursor: None,
+            track_changes: false,
+            auto_fit: true,
+            zoom: 1,
+            aggregation: Aggregation::default(),
+            is_lit: None,
+        }
+    }
+
+    /// Summarizes blocks of coordinates with the given aggregation.
+    /// `Aggregation::Braille` and `Aggregation::HalfBlock` show 2x4 and 1x2
+    /// coordinates per character without zooming out.
+    pub fn with_aggregation(mut self, aggregation: Aggregation) -> Self {
+        self.aggregation = aggregation;
+        self
+    }
+
+    /// Uses the given predicate to tell which values are lit
+    pub fn with_lit_predicate(mut self, is_lit: impl Fn(&V) -> bool + 'static) -> Self {
+        self.is_lit = Some(Box::new(is_lit));
+        self
+    }
+
+    /// Cell showing the given value, and whether it is lit
+    fn shown(&self, value: &V, blank: Cell) -> (Cell, bool) {
+        let cell = value.to_cell();
+        let lit = match self.is_lit {
+            Some(ref is_lit) => is_lit(value),
+            None => cell != blank,
+        };
+        (cell, lit)
+    }
+
+    /// Cell summarizing the block of coordinates drawn at the given local
+    /// position of the viewport, in the given frame
+    fn cell_at(&self, frame: &Frame<C, usize, V>, position: (usize, usize)) -> Cell {
+        let (scale_x, scale_y) = self.scale();
+        let min_x = self.origin.0 + position.0 as i64 * scale_x;
+        let min_y = self.origin.1 + position.1 as i64 * scale_y;
+        let blank = frame.default_value().to_cell();
+        let block: Vec<(Cell, bool)> = (min_y..min_y + scale_y)
+            .flat_map(|y| (min_x..min_x + scale_x).map(move |x| C::from_xy(x, y)))
+            .map(|c| match c {
+                Some(c) => self.shown(frame.get(&c), blank),
+                None => (blank, false),
+            })
+            .collect();
+        self.aggregation.aggregate(&block, scale_x as usize)
+    }
+
+    /// Whether the viewport shows more coordinates in the given area (as
+    /// a number of terminal characters) than the DiffCache stores. If so,
+    /// going through the stored coordinates is cheaper than through every
+    /// coordinate shown
+    fn covers_more_than(&self, characters: (usize, usize), stored: usize) -> bool {
+        let (scale_x, scale_y) = self.scale();
+        let area = scale_x as u128 * scale_y as u128;
+        area.saturating_mul(characters.0 as u128 * characters.1 as u128) > stored as u128
+    }
+
+    /// Local position of the block drawn at the given logical X/Y coordinates,
+    /// and their (X, Y) in this block, if they are in the viewport
+    fn block_at(&self, x: i64, y: i64) -> Option<((usize, usize), (usize, usize))> {
+        let (scale_x, scale_y) = self.scale();
+        let locate = |offset: i128, scale: i64, characters: usize| {
+            let position = offset.div_euclid(scale as i128);
+            if offset < 0 || position >= characters as i128 {
+                return None;
+            }
+            Some((position as usize, offset.rem_euclid(scale as i128) as usize))
+        };
+        let (column, x) = locate(x as i128 - self.origin.0 as i128, scale_x, self.size.x)?;
+        let (row, y) = locate(y as i128 - self.origin.1 as i128, scale_y, self.size.y)?;
+        Some(((column, row), (x, y)))
+    }
+
+    /// Coordinates of the viewport that got a value by the given frame, as
+    /// ((X, Y) in their block, Cell, lit), grouped by the local position of
+    /// the block drawn there
+    fn stored_blocks(&self, frame: &Frame<C, usize, V>) -> HashMap<(usize, usize), StoredBlock> {
+        let blank = frame.default_value().to_cell();
+        let mut blocks: HashMap<(usize, usize), StoredBlock> = HashMap::new();
+        frame.iter().for_each(|(c, value)| {
+            if let Some((position, offset)) = self.block_at(c.x(), c.y()) {
+                let (cell, lit) = self.shown(value, blank);
+                blocks
+                    .entry(position)
+                    .or_default()
+                    .push((offset, cell, lit));
+            }
+        });
+        blocks
+    }
+
+    /// Number of (X, Y) coordinates shown by each terminal character
+    pub fn scale(&self) -> (i64, i64) {
+        let (columns, rows) = self.aggregation.subpixels();
+        ((columns * self.zoom) as i64, (rows * self.zoom) as i64)
+    }
+
+    /// Number of (X, Y) coordinates shown by the whole viewport
+    pub fn span(&self) -> (i64, i64) {
+        let (x, y) = self.scale();
+        (x * self.size.x as i64, y * self.size.y as i64)
+    }
+
+    /// Smallest zoom for the given bounds to fit in the viewport
+    fn fitting_zoom(&self, bounds: &Bounds) -> usize {
+        let (columns, rows) = self.aggregation.subpixels();
+        let zoom = |length: u64, characters: usize, subpixels: usize| {
+            usize::try_from(length)
+                .unwrap_or(usize::MAX)
+                .div_ceil(characters.saturating_mul(subpixels).max(1))
+        };
+        zoom(bounds.width(), self.size.x, columns)
+            .max(zoom(bounds.height(), self.size.y, rows))
+            .max(1)
+    }
+
+    /// Sets the width and height of the block of coordinates drawn by each
+    /// subpixel, keeping the center of the viewport in place.
+    /// The zoom is at most the one fitting all the content in the viewport
+    pub fn set_zoom(&mut self, zoom: usize) {
+        let (x, y) = self.center();
+        let max = match self.target.snapshot().bounds() {
+            Some(bounds) => self.fitting_zoom(&bounds),
+            None => 1,
+        };
+        self.zoom = zoom.clamp(1, max);
+        self.center_on(x, y);
+    }
+
+    /// Doubles the number of coordinates drawn by each subpixel
+    pub fn zoom_out(&mut self) {
+        self.set_zoom(self.zoom.saturating_mul(2));
+    }
+
+    /// Halves the number of coordinates drawn by each subpixel
+    pub fn zoom_in(&mut self) {
+        self.set_zoom(self.zoom / 2);
+    }
+
+    /// Switches to the next way of summarizing the blocks of coordinates,
+    /// keeping the center of the viewport in place
+    pub fn next_aggregation(&mut self) {
+        let (x, y) = self.center();
+        self.aggregation = self.aggregation.next();
+        self.center_on(x, y);
+    }
+
+    /// Logical X/Y coordinates of the center of the viewport
+    pub fn center(&self) -> (i64, i64) {
+        let (width, height) = self.span();
+        (self.origin.0 + width / 2, self.origin.1 + height / 2)
+    }
+
+    /// Moves the viewport so that its center is at the given position
+    pub fn center_on(&mut self, x: i64, y: i64) {
+        let (width, height) = self.span();
+        self.origin = (x - width / 2, y - height / 2);
+    }
+
+    /// Bounds of the content of the current frame, i.e. of the coordinates
+    /// whose value is shown differently from the default value, if any
+    fn content_bounds(&self) -> Option<Bounds> {
+        let index = { self.time_index.lock().unwrap().current };
+        let target = self.target.snapshot();
+        let frame = target.frame(index);
+        let blank = frame.default_value().to_cell();
+        Bounds::of(
+            frame
+                .iter()
+                .filter(|(_, v)| v.to_cell() != blank)
+                .map(|(c, _)| c),
+        )
+    }
+
+    /// Moves the viewport to show as much of the content of the current
+    /// frame as possible: content smaller than the viewport is centered,
+    /// bigger content is shown from its top-left corner.
+    /// Returns false if there is no content in this frame.
+    pub fn fit_content(&mut self) -> bool {
+        match self.content_bounds() {
+            Some(bounds) => {
+                self.fit_bounds(bounds);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Moves the viewport to show as much of the given bounds as possible
+    fn fit_bounds(&mut self, bounds: Bounds) {
+        let (width, height) = self.span();
+        let fit = |min: i64, length: u64, available: i64| {
+            let length = i64::try_from(length).unwrap_or(i64::MAX);
+            min.saturating_sub((available - length).max(0) / 2)
+        };
+        self.origin = (
+            fit(bounds.min.0, bounds.width(), width),
+            fit(bounds.min.1, bounds.height(), height),
+        );
+    }
+
+    /// Zooms out just enough for the whole content of the current frame to
+    /// fit in the viewport, and fits the viewport on it.
+    /// Returns false if there is no content in this frame.
+    pub fn zoom_to_fit(&mut self) -> bool {
+        let bounds = match self.content_bounds() {
+            Some(bounds) => bounds,
+            None => return false,
+        };
+        self.zoom = self.fitting_zoom(&bounds);
+        self.fit_bounds(bounds);
+        true
+    }
+
+    /// Moves the viewport so that its center is the center of the content
+    /// of the current frame.
+    /// Returns false if there is no content in this frame.
+    pub fn center_content(&mut self) -> bool {
+        match self.content_bounds() {
+            Some(bounds) => {
+                let (x, y) = bounds.center();
+                self.center_on(x, y);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Enables or disables centering the viewport on the changes of the current frame
+    pub fn toggle_track_changes(&mut self) {
+        self.track_changes = !self.track_changes;
+    }
+
+    /// Moves the viewport where it should be after the time frame or the
+    /// content changed
+    fn update_origin(&mut self) {
+        if self.auto_fit {
+            if let Some(bounds) = self.content_bounds() {
+                self.auto_fit = false;
+                let (width, height) = self.span();
+                let visible = bounds.max.0 >= self.origin.0
+                    && bounds.max.1 >= self.origin.1
+                    && bounds.min.0 < self.origin.0 + width
+                    && bounds.min.1 < self.origin.1 + height;
+                if !visible {
+                    self.fit_bounds(bounds);
+                }
+            }
+        }
+
+        if self.track_changes {
+            let index = { self.time_index.lock().unwrap().current };
+            let centroid = self
+                .target
+                .snapshot()
+                .extent_at(index)
+                .map(|e| e.centroid());
+            if let Some((x, y)) = centroid {
+                self.center_on(x, y);
+            }
         }
     }
 
     /// Moves the viewport in the given direction
-    pub fn move_center(&mut self, x: i32, y: i32) {
+    pub fn move_center(&mut self, x: i64, y: i64) {
         self.origin.0 += x;
         self.origin.1 += y;
     }
 
+    /// Shows the cursor at the center of the viewport, or hides it
+    pub fn toggle_cursor(&mut self) {
+        self.cursor = match self.cursor {
+            Some(_) => None,
+            None => Some(self.center()),
+        };
+    }
+
+    /// Moves the cursor in the given direction if it is shown, panning the
+    /// viewport to keep it visible. Moves the viewport otherwise.
+    /// Both move by one terminal character, whatever the zoom.
+    pub fn move_cursor_or_center(&mut self, x: i64, y: i64) {
+        let (scale_x, scale_y) = self.scale();
+        let (x, y) = (x * scale_x, y * scale_y);
+        let (cursor_x, cursor_y) = match self.cursor {
+            Some((cursor_x, cursor_y)) => (cursor_x + x, cursor_y + y),
+            None => return self.move_center(x, y),
+        };
+        self.cursor = Some((cursor_x, cursor_y));
+
+        let (width, height) = self.span();
+        self.origin.0 = self.origin.0.min(cursor_x).max(cursor_x - width + 1);
+        self.origin.1 = self.origin.1.min(cursor_y).max(cursor_y - height + 1);
+    }
+
     /// Specifies the new time index to move to
     pub fn move_to_time_index(&mut self, new_index: usize) {
         self.time_index.lock().unwrap().set_current(new_index);
@@ -56,93 +377,240 @@ impl FrameView {
         self.time_index.lock().unwrap().backward();
     }
 
+    /// Moves the time index to the next or previous frame where a visible cell
+    /// changes, or where the cell under the cursor changes when it is shown.
+    /// Returns false if there is no such frame.
+    pub fn seek_change(&mut self, direction: TimeDirection) -> bool {
+        let cursor = self.cursor.and_then(|(x, y)| C::from_xy(x, y));
+        let current = { self.time_index.lock().unwrap().current };
+
+        let change = {
+            let target = self.target.snapshot();
+            let coords: Vec<C> = match cursor {
+                Some(coords) => vec![coords],
+                // Looks for the stored coordinates in the viewport when it
+                // shows more of them
+                None if self.covers_more_than((self.size.x, self.size.y), target.len()) => target
+                    .coords()
+                    .filter(|c| self.block_at(c.x(), c.y()).is_some())
+                    .collect(),
+                None => self.get_screen_coords().flatten().collect(),
+            };
+            match direction {
+                TimeDirection::Forward => target.next_change_in(coords.iter(), current),
+                TimeDirection::Backward => target.previous_change_in(coords.iter(), current),
+            }
+        };
+        match change {
+            Some(index) => self.time_index.lock().unwrap().set_current(index),
+            None => false,
+        }
+    }
+
     /// Generates the logical coordinates of the viewport
-    pub fn get_screen_coords(&self) -> impl Iterator<Item = (i32, i32)> {
+    /// Positions that can not be represented by the C type are None
+    pub fn get_screen_coords(&self) -> impl Iterator<Item = Option<C>> {
+        let (width, height) = self.span();
         let min_x = self.origin.0;
-        let max_x = self.origin.0 + self.size.x as i32;
+        let max_x = self.origin.0 + width;
         let min_y = self.origin.1;
-        let max_y = self.origin.1 + self.size.y as i32;
-        (min_x..max_x).flat_map(move |x| (min_y..max_y).map(move |y| (x, y)))
+        let max_y = self.origin.1 + height;
+        (min_x..max_x).flat_map(move |x| (min_y..max_y).map(move |y| C::from_xy(x, y)))
+    }
+
+    /// Logical coordinates of the given position of the viewport, if they
+    /// can be represented by the C type.
+    /// When zoomed out, these are the top-left coordinates of the block drawn there.
+    pub fn coords_at(&self, position: Vec2) -> Option<C> {
+        if position.x >= self.size.x || position.y >= self.size.y {
+            return None;
+        }
+        let (scale_x, scale_y) = self.scale();
+        C::from_xy(
+            self.origin.0 + position.x as i64 * scale_x,
+            self.origin.1 + position.y as i64 * scale_y,
+        )
+    }
+}
+
+impl<C: Coords, V: Displayable + Debug> FrameView<C, V> {
+    /// Handles a mouse event happening at the given position of the viewport:
+    /// * dragging with the left button pans the viewport,
+    /// * clicking a cell opens an Inspector on it,
+    /// * the wheel steps the time.
+    fn on_mouse(&mut self, position: Vec2, event: MouseEvent) -> EventResult {
+        match event {
+            MouseEvent::Press(MouseButton::Left) => {
+                self.drag = Some(position);
+                self.dragged = false;
+            }
+            MouseEvent::Hold(MouseButton::Left) => {
+                if let Some(last) = self.drag {
+                    let (scale_x, scale_y) = self.scale();
+                    self.move_center(
+                        (last.x as i64 - position.x as i64) * scale_x,
+                        (last.y as i64 - position.y as i64) * scale_y,
+                    );
+                    self.dragged |= last != position;
+                    self.drag = Some(position);
+                }
+            }
+            MouseEvent::Release(MouseButton::Left) => {
+                if self.drag.take().is_some() && !self.dragged {
+                    if let Some(coords) = self.coords_at(position) {
+                        self.cursor = Some((coords.x(), coords.y()));
+                        return self.inspect(coords);
+                    }
+                }
+            }
+            MouseEvent::WheelUp => self.time_backward(),
+            MouseEvent::WheelDown => self.time_forward(),
+            _ => return EventResult::Ignored,
+        }
+        EventResult::Consumed(None)
+    }
+
+    /// Opens an Inspector on the cell at the given coordinates
+    fn inspect(&self, coords: C) -> EventResult {
+        let index = { self.time_index.lock().unwrap().current };
+        let inspector = Inspector::new(&self.target.snapshot(), &coords, index);
+        let time_index = self.time_index.clone();
+        EventResult::with_cb(move |c| c.add_layer(inspector.dialog(time_index.clone())))
+    }
+
+    /// Opens an Inspector on the cell under the cursor, if it is shown
+    fn inspect_cursor(&self) -> EventResult {
+        match self.cursor.and_then(|(x, y)| C::from_xy(x, y)) {
+            Some(coords) => self.inspect(coords),
+            None => EventResult::Ignored,
+        }
     }
 }
 
-impl View for FrameView {
-    /// Draws the FrameView using the given Printer
-    /// FIXME: There should be a way to throw the lock when we're done by copying instead of taking
-    /// a reference. This would reduce the lock time, but does the cloning overhead make it worth ?
+impl<C: Coords, V: Displayable + Debug> View for FrameView<C, V> {
+    /// Draws the FrameView using the given Printer.
+    /// Draws from a snapshot of the DiffCache, so that the frames keep
+    /// being written meanwhile
     fn draw(&self, printer: &Printer) {
         // Creates local coordinates
         let local_coords =
             (0..self.size.x).flat_map(move |x| (0..self.size.y).map(move |y| (x, y)));
 
         let index = { self.time_index.lock().unwrap().current };
+        let (scale_x, scale_y) = self.scale();
 
-        // Maps each coord to the view of the DiffCache
+        // Position of the cursor in local coordinates, if it is visible
+        let cursor = self.cursor.and_then(|(x, y)| {
+            let x = x - self.origin.0;
+            let y = y - self.origin.1;
+            if x < 0 || y < 0 {
+                return None;
+            }
+            Some(((x / scale_x) as usize, (y / scale_y) as usize))
+        });
+
+        // Summarizes the block drawn at each local coord from the view of the DiffCache
         // Displays everything using the given printer
-        self.target
-            .lock()
-            .unwrap()
-            .view(self.get_screen_coords(), index)
-            .zip(local_coords)
-            .for_each(|(v, coord)| printer.print(coord, &v.to_string()))
+        let target = self.target.snapshot();
+        let frame = target.frame(index);
+        // Blocks covering more coordinates than stored are summarized from
+        // the stored coordinates in them
+        let stored = if self.covers_more_than((1, 1), target.len()) {
+            Some(self.stored_blocks(&frame))
+        } else {
+            None
+        };
+        let unset = self.shown(frame.default_value(), frame.default_value().to_cell());
+        local_coords.for_each(|coord| {
+            let mut cell = match stored {
+                Some(ref blocks) => {
+                    let cells = blocks.get(&coord).map_or(&[][..], |b| &b[..]);
+                    let size = (scale_x as usize, scale_y as usize);
+                    self.aggregation.aggregate_sparse(cells, size, unset)
+                }
+                None => self.cell_at(&frame, coord),
+            };
+            if Some(coord) == cursor {
+                cell.effects.insert(Effect::Reverse);
+            }
+            printer.with_color(cell.color_style(), |p| {
+                p.with_effects(cell.effects, |p| p.print(coord, &cell.glyph.to_string()))
+            })
+        })
     }
 
     /// Handles different input events arriving on the Frame
     fn on_event(&mut self, event: Event) -> EventResult {
         match event {
-            // Basic keys (frame movement)
+            // Basic keys (frame movement, or cursor movement when it is shown)
             // Includes HJKL movement
-            Event::Key(k) if k == Key::Left => {
-                self.move_center(-1, 0);
-                return EventResult::Consumed(None);
-            }
-            Event::Char(c) if c == 'h' => {
-                self.move_center(-1, 0);
-                return EventResult::Consumed(None);
-            }
-            Event::Key(k) if k == Key::Down => {
-                self.move_center(0, 1);
-                return EventResult::Consumed(None);
-            }
-            Event::Char(c) if c == 'j' => {
-                self.move_center(0, 1);
-                return EventResult::Consumed(None);
-            }
-            Event::Key(k) if k == Key::Up => {
-                self.move_center(0, -1);
-                return EventResult::Consumed(None);
-            }
-            Event::Char(c) if c == 'k' => {
-                self.move_center(0, -1);
-                return EventResult::Consumed(None);
+            Event::Key(Key::Left) | Event::Char('h') => self.move_cursor_or_center(-1, 0),
+            Event::Key(Key::Down) | Event::Char('j') => self.move_cursor_or_center(0, 1),
+            Event::Key(Key::Up) | Event::Char('k') => self.move_cursor_or_center(0, -1),
+            Event::Key(Key::Right) | Event::Char('l') => self.move_cursor_or_center(1, 0),
+            // Content handling:
+            // * fits / centers the viewport on the content
+            Event::Char('F') => {
+                self.fit_content();
             }
-            Event::Key(k) if k == Key::Right => {
-                self.move_center(1, 0);
-                return EventResult::Consumed(None);
+            Event::Char('C') => {
+                self.center_content();
             }
-            Event::Char(c) if c == 'l' => {
-                self.move_center(1, 0);
-                return EventResult::Consumed(None);
+            // * centers the viewport on the changes of each frame
+            Event::Char('t') => self.toggle_track_changes(),
+            // Zoom handling:
+            // * zooms in / out
+            Event::Char('z') => self.zoom_in(),
+            Event::Char('Z') => self.zoom_out(),
+            // * zooms out until the whole content fits (o = overview)
+            Event::Char('o') => {
+                self.zoom_to_fit();
             }
+            // * switches the way blocks of cells are summarized when zoomed out
+            Event::Char('a') => self.next_aggregation(),
+            // Cursor handling:
+            // * shows / hides the cursor
+            Event::Char('c') => self.toggle_cursor(),
+            // * inspects the cell under the cursor
+            Event::Key(Key::Enter) | Event::Char('i') => return self.inspect_cursor(),
             // Time handling:
             // * moves forward (n = next)
-            Event::Char(c) if c == 'n' => {
-                self.time_forward();
-                return EventResult::Consumed(None);
-            }
+            Event::Char('n') => self.time_forward(),
             // * backwards (b = back)
-            Event::Char(c) if c == 'b' => {
-                self.time_backward();
-                return EventResult::Consumed(None);
+            Event::Char('b') => self.time_backward(),
+            // * seeks the next / previous frame where something visible changes
+            Event::Char('N') => {
+                self.seek_change(TimeDirection::Forward);
+            }
+            Event::Char('B') => {
+                self.seek_change(TimeDirection::Backward);
             }
-            _ => EventResult::Ignored,
+            // Playback handling:
+            // * play / pause
+            Event::Char(' ') => self.time_index.lock().unwrap().toggle_play(),
+            // * faster / slower
+            Event::Char('+') | Event::Char('=') => self.time_index.lock().unwrap().speed_up(),
+            Event::Char('-') => self.time_index.lock().unwrap().slow_down(),
+            // * reverse direction
+            Event::Char('r') => self.time_index.lock().unwrap().reverse(),
+            // * follow the newest frames as they are generated
+            Event::Char('f') => self.time_index.lock().unwrap().toggle_follow(),
+            // Mouse handling
+            Event::Mouse {
+                offset,
+                position,
+                event,
+            } => return self.on_mouse(position.saturating_sub(offset), event),
+            _ => return EventResult::Ignored,
         }
+        EventResult::Consumed(None)
     }
 
     /// Called once the size of this view has been decided ; Changes the
     /// size to fit accordingly
     fn layout(&mut self, size: Vec2) {
         self.size = size;
+        self.update_origin();
     }
 
     /// Minimum size that we require, given the constraints. Let's just set fullscreen.
@@ -155,3 +623,193 @@ impl View for FrameView {
         true
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff_cache::DiffCache;
+
+    #[test]
+    pub fn frame_view_cursor() {
+        let cache = SharedDiffCache::new(DiffCache::new(' '));
+        let time_index = Arc::new(Mutex::new(TimeIndex::new(0, 0, 0)));
+        let mut view: FrameView<(i32, i32), char> = FrameView::new(cache, time_index);
+        view.layout(Vec2::new(10, 4));
+
+        // Without the cursor, the viewport moves
+        view.move_cursor_or_center(1, 0);
+        assert_eq!(view.origin, (1, 0));
+
+        view.toggle_cursor();
+        assert_eq!(view.cursor, Some((6, 2)));
+        view.move_cursor_or_center(0, 1);
+        assert_eq!((view.cursor, view.origin), (Some((6, 3)), (1, 0)));
+        // The viewport follows the cursor once it leaves it
+        view.move_cursor_or_center(0, 1);
+        assert_eq!((view.cursor, view.origin), (Some((6, 4)), (1, 1)));
+
+        view.toggle_cursor();
+        assert_eq!(view.cursor, None);
+    }
+
+    #[test]
+    pub fn frame_view_fit() {
+        let mut cache = DiffCache::new(' ');
+        let infos = vec![
+            ((-30, -10), 1, 'a'),
+            ((-25, -9), 1, 'b'),
+            ((-20, -3), 2, 'c'),
+            ((-30, -10), 3, ' '),
+        ];
+        cache.append(infos.into_iter()).unwrap();
+        let time_index = Arc::new(Mutex::new(TimeIndex::new(0, 3, 1)));
+        let mut view: FrameView<(i32, i32), char> =
+            FrameView::new(SharedDiffCache::new(cache), time_index.clone());
+
+        // Nothing is visible from (0, 0): the content is fitted right away
+        view.layout(Vec2::new(10, 40));
+        assert_eq!(view.origin, (-32, -29));
+        // Only once
+        view.move_center(5, 0);
+        view.layout(Vec2::new(10, 40));
+        assert_eq!(view.origin, (-27, -29));
+
+        assert!(view.center_content());
+        assert_eq!(view.origin, (-33, -30));
+
+        // The content of the current frame is fitted, without the cells set
+        // back to the default value
+        view.move_to_time_index(2);
+        assert!(view.fit_content());
+        assert_eq!(view.origin, (-30, -26));
+        view.move_to_time_index(3);
+        assert!(view.fit_content());
+        assert_eq!(view.origin, (-27, -25));
+        view.move_to_time_index(1);
+
+        view.toggle_track_changes();
+        view.layout(Vec2::new(10, 40));
+        assert_eq!(view.origin, (-32, -29));
+        time_index.lock().unwrap().forward();
+        view.layout(Vec2::new(10, 40));
+        assert_eq!(view.origin, (-25, -23));
+    }
+
+    #[test]
+    pub fn frame_view_zoom() {
+        let mut cache = DiffCache::new(' ');
+        let infos = vec![((0, 0), 1, 'a'), ((99, 39), 1, 'b')];
+        cache.append(infos.into_iter()).unwrap();
+        let time_index = Arc::new(Mutex::new(TimeIndex::new(0, 1, 1)));
+        let mut view: FrameView<(i32, i32), char> =
+            FrameView::new(SharedDiffCache::new(cache), time_index);
+        view.layout(Vec2::new(10, 4));
+
+        assert!(view.zoom_to_fit());
+        assert_eq!(
+            (view.zoom, view.span(), view.origin),
+            (10, (100, 40), (0, 0))
+        );
+        assert_eq!(view.coords_at(Vec2::new(3, 2)), Some((30, 20)));
+        // Zooming out stops once the whole content fits
+        view.zoom_out();
+        assert_eq!((view.zoom, view.origin), (10, (0, 0)));
+
+        // Half-blocks draw two rows of blocks per character
+        view.next_aggregation();
+        view.next_aggregation();
+        assert_eq!(view.aggregation, Aggregation::HalfBlock);
+        assert_eq!((view.span(), view.origin), ((100, 80), (0, -20)));
+
+        // Zooming keeps the center in place
+        view.zoom_in();
+        assert_eq!((view.scale(), view.origin), ((5, 10), (25, 0)));
+        // Moving pans by one character
+        view.move_cursor_or_center(1, -1);
+        assert_eq!(view.origin, (30, -10));
+    }
+
+    #[test]
+    pub fn frame_view_pixels() {
+        let mut cache = DiffCache::new('.');
+        let infos = vec![((0, 0), 0, '#'), ((1, 3), 0, '#'), ((2, 0), 0, 'o')];
+        cache.append(infos.into_iter()).unwrap();
+        let time_index = Arc::new(Mutex::new(TimeIndex::new(0, 0, 0)));
+        let shared = SharedDiffCache::new(cache);
+        let view: FrameView<(i32, i32), char> =
+            FrameView::new(shared.clone(), time_index).with_aggregation(Aggregation::Braille);
+        let cache = shared.snapshot();
+        let frame = cache.frame(0);
+        assert_eq!(view.cell_at(&frame, (0, 0)).glyph, '⢁');
+        assert_eq!(view.cell_at(&frame, (1, 0)).glyph, '⠁');
+
+        // Only the values matching the predicate are lit
+        let view = view.with_lit_predicate(|v| *v == '#');
+        assert_eq!(view.cell_at(&frame, (0, 0)).glyph, '⢁');
+        assert_eq!(view.cell_at(&frame, (1, 0)).glyph, ' ');
+        let view = view.with_aggregation(Aggregation::HalfBlock);
+        assert_eq!(view.cell_at(&frame, (0, 0)).glyph, '▀');
+        assert_eq!(view.cell_at(&frame, (1, 1)).glyph, '▄');
+    }
+
+    #[test]
+    pub fn frame_view_seek_change() {
+        let mut cache = DiffCache::new(' ');
+        let infos = vec![((0, 0), 2, 'a'), ((20, 0), 5, 'b'), ((1, 1), 9, 'c')];
+        cache.append(infos.into_iter()).unwrap();
+        let time_index = Arc::new(Mutex::new(TimeIndex::new(0, 10, 0)));
+        let mut view: FrameView<(i32, i32), char> =
+            FrameView::new(SharedDiffCache::new(cache), time_index.clone());
+        view.layout(Vec2::new(10, 4));
+
+        // (20, 0) is out of the viewport
+        assert!(view.seek_change(TimeDirection::Forward));
+        assert_eq!(time_index.lock().unwrap().current, 2);
+        assert!(view.seek_change(TimeDirection::Forward));
+        assert_eq!(time_index.lock().unwrap().current, 9);
+        assert!(!view.seek_change(TimeDirection::Forward));
+
+        // Only the cell under the cursor is considered when it is shown
+        view.cursor = Some((0, 0));
+        assert!(view.seek_change(TimeDirection::Backward));
+        assert_eq!(time_index.lock().unwrap().current, 2);
+        assert!(!view.seek_change(TimeDirection::Backward));
+    }
+
+    #[test]
+    pub fn frame_view_sparse() {
+        // Two cells a billion coordinates apart, zoomed out to show both
+        let mut cache = DiffCache::new('.');
+        let far = 1_000_000_000;
+        let infos = vec![((0, 0), 1, '#'), ((far, far - 1), 2, 'o')];
+        cache.append(infos.into_iter()).unwrap();
+        let time_index = Arc::new(Mutex::new(TimeIndex::new(0, 2, 0)));
+        let shared = SharedDiffCache::new(cache);
+        let mut view: FrameView<(i32, i32), char> =
+            FrameView::new(shared.clone(), time_index.clone())
+                .with_aggregation(Aggregation::Braille);
+        view.layout(Vec2::new(10, 4));
+        view.move_to_time_index(2);
+        assert!(view.zoom_to_fit());
+
+        // Only the stored coordinates are gone through
+        let cache = shared.snapshot();
+        let blocks = view.stored_blocks(&cache.frame(2));
+        assert_eq!(blocks.len(), 2);
+        let (scale_x, scale_y) = view.scale();
+        let size = (scale_x as usize, scale_y as usize);
+        let unset = (Cell::new('.'), false);
+        let cell = |position| {
+            let cells = &blocks[&position];
+            view.aggregation.aggregate_sparse(cells, size, unset)
+        };
+        // Both are in the right column of dots, the content being centered
+        assert_eq!(cell((0, 0)).glyph, '⠈');
+        assert_eq!(cell((8, 3)).glyph, '⢀');
+
+        assert!(view.seek_change(TimeDirection::Backward));
+        assert_eq!(time_index.lock().unwrap().current, 1);
+        assert!(view.seek_change(TimeDirection::Forward));
+        assert_eq!(time_index.lock().unwrap().current, 2);
+    }
+}
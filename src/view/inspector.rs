@@ -0,0 +1,121 @@
+use crate::coords::Coords;
+use crate::diff_cache::DiffCache;
+use crate::time_index::TimeIndex;
+use cursive::view::{Boxable, Scrollable};
+use cursive::views::{Dialog, DummyView, LinearLayout, SelectView, TextView};
+use cursive::Cursive;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+
+/// Snapshot of the value stored at one coordinate of a DiffCache, and of
+/// its history, shown in a dialog
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Inspector {
+    /// Inspected coordinates
+    coords: String,
+    /// Time index at which the value is shown
+    index: usize,
+    /// Value at this time index
+    value: String,
+    /// Every change of the value, as (index, value)
+    history: Vec<(usize, String)>,
+}
+
+impl Inspector {
+    /// Creates a new instance of the Inspector, copying the value at the given
+    /// coordinates and time index out of the cache
+    pub fn new<C: Coords, V: Debug>(
+        cache: &DiffCache<C, usize, V>,
+        coords: &C,
+        index: usize,
+    ) -> Self {
+        Inspector {
+            coords: format!("{:?}", coords),
+            index,
+            value: format!("{:?}", cache.search_or_default(coords.clone(), index)),
+            history: cache
+                .history(coords)
+                .map(|(i, v)| (*i, format!("{:?}", v)))
+                .collect(),
+        }
+    }
+
+    /// Latest change of the value at or before the shown time index, if any
+    pub fn last_change(&self) -> Option<usize> {
+        self.history
+            .iter()
+            .rev()
+            .map(|(i, _)| *i)
+            .find(|i| *i <= self.index)
+    }
+
+    /// Text describing the value at the shown time index, and when it last changed
+    pub fn summary(&self) -> String {
+        let last_change = match self.last_change() {
+            Some(i) if i == self.index => "Changed at this index".to_string(),
+            Some(i) => format!("Last changed at index {} ({} ago)", i, self.index - i),
+            None => "Never changed before this index".to_string(),
+        };
+        format!("At index {}: {}\n{}", self.index, self.value, last_change)
+    }
+
+    /// Creates the dialog showing this Inspector.
+    /// Submitting one of the listed changes moves the TimeIndex to it.
+    pub fn dialog(&self, time_index: Arc<Mutex<TimeIndex>>) -> Dialog {
+        let mut changes = SelectView::new().on_submit(move |c: &mut Cursive, index: &usize| {
+            time_index.lock().unwrap().set_current(*index);
+            c.pop_layer();
+        });
+        self.history
+            .iter()
+            .for_each(|(i, v)| changes.add_item(format!("{}: {}", i, v), *i));
+        if let Some(pos) = self
+            .last_change()
+            .and_then(|last| self.history.iter().position(|(i, _)| *i == last))
+        {
+            changes.set_selection(pos);
+        }
+
+        let content = LinearLayout::vertical()
+            .child(TextView::new(self.summary()))
+            .child(DummyView)
+            .child(TextView::new(format!("Changes ({}):", self.history.len())))
+            .child(changes.scrollable().max_height(CHANGES_HEIGHT));
+        Dialog::around(content)
+            .title(format!("Cell {}", self.coords))
+            .dismiss_button("Close")
+    }
+}
+
+/// Maximum number of changes shown at once by the dialog
+const CHANGES_HEIGHT: usize = 15;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn inspector_last_change() {
+        let mut cache: DiffCache<(i32, i32), usize, char> = DiffCache::new('.');
+        let infos = vec![((0, 0), 1, 'a'), ((0, 0), 4, 'b')];
+        cache.append(infos.into_iter()).unwrap();
+
+        let inspector = Inspector::new(&cache, &(0, 0), 3);
+        assert_eq!(inspector.last_change(), Some(1));
+        assert_eq!(
+            inspector.summary(),
+            "At index 3: 'a'\nLast changed at index 1 (2 ago)"
+        );
+        let inspector = Inspector::new(&cache, &(0, 0), 4);
+        assert_eq!(
+            inspector.summary(),
+            "At index 4: 'b'\nChanged at this index"
+        );
+        let inspector = Inspector::new(&cache, &(1, 0), 2);
+        assert_eq!(inspector.last_change(), None);
+        assert_eq!(
+            inspector.summary(),
+            "At index 2: '.'\nNever changed before this index"
+        );
+    }
+}
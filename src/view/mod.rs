@@ -1,2 +1,4 @@
+pub mod aggregation;
 pub mod frame;
+pub mod inspector;
 pub mod time_view;
@@ -1,5 +1,6 @@
-use crate::time_index::TimeIndex;
+use crate::time_index::{Direction as PlaybackDirection, Generation, TimeIndex};
 use cursive::direction::Direction;
+use cursive::event::{Event, EventResult, MouseButton, MouseEvent};
 use cursive::theme::{Color, ColorStyle, ColorType};
 use cursive::vec::Vec2;
 use cursive::view::View;
@@ -33,11 +34,33 @@ impl TimeView {
             Err(_) => false,
         }
     }
+
+    /// Moves the time index to the one shown at the given column of the bar
+    pub fn seek_at(&mut self, x: usize) -> bool {
+        if self.size.x < 4 {
+            return false;
+        }
+        let x = x.max(1).min(self.size.x - 2);
+        let mut ti = self.time_index.lock().unwrap();
+        let index = map(x, 1, self.size.x - 2, ti.min, ti.max);
+        (*ti).set_current(index)
+    }
+}
+
+/// Gives the focus back to the FrameView, so that it gets the keyboard events
+fn focus_frame_view() -> EventResult {
+    EventResult::with_cb(|c| {
+        let _ = c.focus_id("frame_view");
+    })
 }
 
 /// Utility function, linearly maps the number x contained in the min range to
-/// a y number contained in the max range
+/// a y number contained in the max range.
+/// An empty min range maps to the start of the max range
 fn map(x: usize, in_min: usize, in_max: usize, out_min: usize, out_max: usize) -> usize {
+    if in_max == in_min {
+        return out_min;
+    }
     (x - in_min) * (out_max - out_min) / (in_max - in_min) + out_min
 }
 
@@ -51,6 +74,50 @@ impl View for TimeView {
 
         printer.print_box((0, 0), self.size, true);
 
+        // Shows the playback state on the top border
+        let playback = &time_index.playback;
+        let str_playback = match (playback.playing, playback.direction) {
+            (false, _) => format!(" paused - {} fps ", playback.fps),
+            (true, PlaybackDirection::Forward) => format!(" playing > {} fps ", playback.fps),
+            (true, PlaybackDirection::Backward) => format!(" playing < {} fps ", playback.fps),
+        };
+        printer.print((2, 0), &str_playback);
+        if time_index.follow {
+            printer.print((str_playback.len() + 2, 0), " following ");
+        }
+
+        // Shows the generation state on the top border
+        let str_generation = match time_index.generation {
+            Generation::Running => " generating... ".to_string(),
+            Generation::Finished(d) => {
+                format!(" generated in {}.{:03}s ", d.as_secs(), d.subsec_millis())
+            }
+            Generation::Failed(d) => {
+                format!(" failed after {}.{:03}s ", d.as_secs(), d.subsec_millis())
+            }
+        };
+        if let Some(x) = self.size.x.checked_sub(str_generation.len() + 2) {
+            printer.print((x, 0), &str_generation);
+        }
+
+        // Shows the breakpoint hits on the bottom border
+        let str_breaks = match time_index.current_break() {
+            Some(name) => format!(" breakpoint hit: {} ", name),
+            None if !time_index.breaks.is_empty() => {
+                format!(" {} breakpoint hits ", time_index.breaks.len())
+            }
+            None => String::new(),
+        };
+        printer.print((2, 3), &str_breaks);
+
+        // Warns on the bottom border when the current frame is approximated
+        if time_index.is_decimated(time_index.current) {
+            let str_decimated = " decimated frame ";
+            if let Some(x) = self.size.x.checked_sub(str_decimated.len() + 2) {
+                printer.print((x, 3), str_decimated);
+            }
+        }
+
         let str_min = format!("{}", time_index.min);
 
         let str_max = format!("{}", time_index.max);
@@ -108,6 +175,29 @@ impl View for TimeView {
             );
         }
 
+        // Shows the decimated ranges of the bar
+        if time_index.max > time_index.min {
+            printer.with_color(
+                ColorStyle::new(
+                    ColorType::Color(Color::Rgb(128, 128, 128)),
+                    ColorType::Color(Color::Rgb(64, 64, 64)),
+                ),
+                |p| {
+                    time_index.decimated.iter().for_each(|r| {
+                        let clamp = |i: usize| i.max(time_index.min).min(time_index.max);
+                        let (start, end) = (clamp(r.start), clamp(r.end.saturating_sub(1)));
+                        let x_start =
+                            map(start, time_index.min, time_index.max, 1, self.size.x - 2);
+                        let x_end = map(end, time_index.min, time_index.max, 1, self.size.x - 2);
+                        p.print(
+                            (x_start, 2),
+                            &(x_start..=x_end).map(|_| ':').collect::<String>(),
+                        );
+                    });
+                },
+            );
+        }
+
         // Prints the current time cursor
         printer.with_color(
             ColorStyle::new(
@@ -120,9 +210,41 @@ impl View for TimeView {
         );
     }
 
-    /// Takes the focus for the TimeView
-    fn take_focus(&mut self, _: Direction) -> bool {
-        false
+    /// Handles mouse events: clicking or dragging on the bar seeks the time
+    /// index, the wheel steps it
+    fn on_event(&mut self, event: Event) -> EventResult {
+        let (position, event) = match event {
+            Event::Mouse {
+                offset,
+                position,
+                event,
+            } => (position.saturating_sub(offset), event),
+            _ => return EventResult::Ignored,
+        };
+        match event {
+            MouseEvent::Press(MouseButton::Left) | MouseEvent::Hold(MouseButton::Left) => {
+                self.seek_at(position.x);
+                EventResult::Consumed(None)
+            }
+            MouseEvent::Release(MouseButton::Left) => {
+                self.seek_at(position.x);
+                focus_frame_view()
+            }
+            MouseEvent::WheelUp => {
+                self.time_index.lock().unwrap().backward();
+                focus_frame_view()
+            }
+            MouseEvent::WheelDown => {
+                self.time_index.lock().unwrap().forward();
+                focus_frame_view()
+            }
+            _ => EventResult::Ignored,
+        }
+    }
+
+    /// Only takes the focus when clicked, to receive the following mouse events
+    fn take_focus(&mut self, source: Direction) -> bool {
+        source == Direction::none()
     }
 
     /// Called when the size of the widget has been decided
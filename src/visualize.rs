@@ -1,9 +1,15 @@
-use crate::diff_cache::DiffCache;
+use crate::coords::Coords;
+use crate::shared_cache::{FrameChanges, SharedDiffCache};
 use crate::time_index::TimeIndex;
+use crossbeam_channel::{Receiver, RecvTimeoutError, TrySendError};
 use itertools::{EitherOrBoth, Itertools};
+use std::collections::BTreeMap;
 use std::hash::Hash;
+use std::panic::{self, AssertUnwindSafe};
 use std::string::ToString;
 use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
 
 /// Trait allowing cargo-aoc to Visualize an implementor
 /// using the display function of the V type
@@ -38,9 +44,8 @@ where
             (x, y) => self
                 .to_string()
                 .lines()
-                .skip(*y as usize)
-                .next()
-                .and_then(|line| line.chars().skip(*x as usize).next()),
+                .nth(*y as usize)
+                .and_then(|line| line.chars().nth(*x as usize)),
         }
     }
 
@@ -80,39 +85,295 @@ where
     }
 }
 
+/// Largest number of frames stored in the cache at once, and waiting to be stored
+const BATCH_FRAMES: usize = 256;
+
+/// Longest delay between two writes to the cache, so that the UI keeps
+/// showing the frames as they come, even when the states stall
+const BATCH_DELAY: Duration = Duration::from_millis(10);
+
+/// Number of states waiting for a worker, per worker
+const JOBS_PER_WORKER: usize = 4;
+
+/// Runs the iterator to completion, storing the values of each yielded state
+/// in the cache, and growing the TimeIndex accordingly.
+/// The frames are stored in batches by another thread, hence `Sync`
 pub fn populate_cache<T, C, V>(
-    cache: Arc<Mutex<DiffCache<C, usize, V>>>,
+    cache: SharedDiffCache<C, V>,
+    time_index: Arc<Mutex<TimeIndex>>,
+    iter: impl Iterator<Item = T>,
+) where
+    T: Visualize<C, V> + std::fmt::Debug + std::default::Default,
+    V: Clone + std::fmt::Debug + Send + Sync,
+    C: Coords + Sync,
+{
+    populate_cache_with(cache, time_index, iter, |_, _, _| ());
+}
+
+/// Same as `populate_cache`, calling `on_frame` once each frame is computed,
+/// before it is stored, with its index, the state it comes from and the
+/// values that changed. Only the previous state is kept afterwards
+pub fn populate_cache_with<T, C, V>(
+    cache: SharedDiffCache<C, V>,
     time_index: Arc<Mutex<TimeIndex>>,
     iter: impl Iterator<Item = T>,
+    mut on_frame: impl FnMut(usize, &T, &[(C, V)]),
 ) where
     T: Visualize<C, V> + std::fmt::Debug + std::default::Default,
-    V: std::fmt::Debug,
-    C: Hash + Eq + std::fmt::Debug,
+    V: Clone + std::fmt::Debug + Send + Sync,
+    C: Coords + Sync,
 {
-    let mut index: usize = 1;
-    let first = T::default();
-    iter.fold(first, |acc, a| {
-        let delta = a.delta(&acc).into_iter().filter_map(|c| {
-            if let Some(v) = a.get(&c) {
-                return Some((c, index, v));
+    let (frames, computed) = crossbeam_channel::bounded(BATCH_FRAMES);
+    let (cache, time_index) = (&cache, &*time_index);
+    std::thread::scope(|scope| {
+        scope.spawn(move || store_batches(cache, time_index, computed));
+
+        let mut previous = T::default();
+        for (index, state) in (1..).zip(iter) {
+            let delta = changes(&state, &previous);
+            on_frame(index, &state, &delta);
+            previous = state;
+            // Stops if the storing thread panicked, the scope then panics too
+            if frames.send((index, delta)).is_err() {
+                break;
             }
-            Some((c, index, a.default_val()))
-        });
+        }
+        drop(frames);
+    });
+}
 
-        // Locks the cache and populate it
-        cache.lock().unwrap().append(delta);
+/// Same as `populate_cache_with`, computing the changes between consecutive
+/// states on the given number of worker threads, while the iterator runs on
+/// the calling one. The frames are still stored, and `on_frame` called, in order.
+/// If computing the changes of a frame panics, the frames before it are still
+/// stored, then the panic is raised again on the calling thread
+pub fn populate_cache_parallel<T, C, V>(
+    cache: SharedDiffCache<C, V>,
+    time_index: Arc<Mutex<TimeIndex>>,
+    iter: impl Iterator<Item = T>,
+    workers: usize,
+    mut on_frame: impl FnMut(usize, &T, &[(C, V)]),
+) where
+    T: Visualize<C, V> + std::fmt::Debug + std::default::Default + Send + Sync,
+    V: Clone + std::fmt::Debug + Send + Sync,
+    C: Coords + Sync,
+{
+    let workers = workers.max(1);
+    let (jobs, queued) = crossbeam_channel::bounded::<Job<T>>(workers * JOBS_PER_WORKER);
+    let (computed, done) = crossbeam_channel::unbounded::<Frame<T, C, V>>();
+    let (frames, ordered) = crossbeam_channel::bounded(BATCH_FRAMES);
+    let (cache, time_index) = (&cache, &*time_index);
 
-        // Locks the TimeIndex and add one to the max index
-        time_index.lock().unwrap().add_max();
+    std::thread::scope(|scope| {
+        scope.spawn(move || store_batches(cache, time_index, ordered));
+        for _ in 0..workers {
+            let (queued, computed) = (queued.clone(), computed.clone());
+            scope.spawn(move || {
+                for (index, previous, state) in queued.iter() {
+                    // A panic is handed over to the calling thread
+                    let delta =
+                        panic::catch_unwind(AssertUnwindSafe(|| changes(&*state, &*previous)));
+                    let failed = delta.is_err();
+                    let _ = computed.send((index, delta.map(|delta| (state, delta))));
+                    if failed {
+                        return;
+                    }
+                }
+            });
+        }
+        // Only the workers hold these, so that their channels disconnect once
+        // they all stopped
+        drop((queued, computed));
+
+        // Frames computed by the workers are stored in order: the ones
+        // computed ahead wait for the ones before them
+        let mut ahead = BTreeMap::new();
+        let mut next = 1;
+        {
+            // Returns false if the frame could not be computed
+            let mut store = |(index, frame): Frame<T, C, V>| {
+                let computed = frame.is_ok();
+                ahead.insert(index, frame);
+                // Stops at the frames that could not be computed, their
+                // panic being raised again once the workers stopped
+                while let Some(Ok(_)) = ahead.get(&next) {
+                    if let Some(Ok((state, delta))) = ahead.remove(&next) {
+                        on_frame(next, &state, &delta);
+                        // The storing thread only stops early if it panicked,
+                        // the scope then panics too
+                        let _ = frames.send((next, delta));
+                    }
+                    next += 1;
+                }
+                computed
+            };
 
-        index += 1;
-        a
+            // Stops feeding the workers once a frame could not be computed,
+            // or once they all stopped
+            let mut previous = Arc::new(T::default());
+            'feed: for (index, state) in (1..).zip(iter) {
+                let state = Arc::new(state);
+                let mut job = (
+                    index,
+                    std::mem::replace(&mut previous, state.clone()),
+                    state,
+                );
+                // Stores the computed frames while waiting for a worker
+                loop {
+                    match jobs.try_send(job) {
+                        Ok(()) => break,
+                        Err(TrySendError::Full(waiting)) => {
+                            if !done.recv().is_ok_and(&mut store) {
+                                break 'feed;
+                            }
+                            job = waiting;
+                        }
+                        Err(TrySendError::Disconnected(_)) => break 'feed,
+                    }
+                }
+                if !done.try_iter().all(&mut store) {
+                    break;
+                }
+            }
+            drop(jobs);
+            done.iter().for_each(|frame| {
+                store(frame);
+            });
+        }
+        drop(frames);
+
+        // Raises the first panic again, once the frames before it are stored
+        if let Some(panic) = ahead.into_values().find_map(Result::err) {
+            panic::resume_unwind(panic);
+        }
     });
 }
 
+/// A state to compare with the previous one, at the given index
+type Job<T> = (usize, Arc<T>, Arc<T>);
+
+/// A state with its index and the values that changed since the previous one,
+/// or the panic raised while computing them
+type Frame<T, C, V> = (usize, thread::Result<(Arc<T>, Vec<(C, V)>)>);
+
+/// Values of the coordinates that changed between the previous state and this one
+fn changes<T, C, V>(state: &T, previous: &T) -> Vec<(C, V)>
+where
+    T: Visualize<C, V>,
+    C: Coords,
+{
+    state
+        .delta(previous)
+        .into_iter()
+        .map(|c| {
+            let v = state.get(&c).unwrap_or_else(|| state.default_val());
+            (c, v)
+        })
+        .collect()
+}
+
+/// Stores the frames received in batches, until their sender is dropped.
+/// A batch is stored once full, or once `BATCH_DELAY` passed since the
+/// previous one, even if no other frame comes in the meantime.
+/// Every frame is published once their sender is dropped
+fn store_batches<C: Coords, V: Clone>(
+    cache: &SharedDiffCache<C, V>,
+    time_index: &Mutex<TimeIndex>,
+    frames: Receiver<FrameChanges<C, V>>,
+) {
+    let mut batch = Batch::new();
+    loop {
+        let received = match batch.due_in() {
+            Some(delay) => frames.recv_timeout(delay),
+            None => frames.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+        match received {
+            Ok(frame) => {
+                batch.frames.push(frame);
+                if batch.is_due() {
+                    batch.flush(cache, time_index);
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => batch.flush(cache, time_index),
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    batch.flush(cache, time_index);
+    cache.sync();
+    batch.show(cache, time_index);
+}
+
+/// Frames waiting to be stored, so that the cache is written once for
+/// several frames
+struct Batch<C, V> {
+    frames: Vec<FrameChanges<C, V>>,
+    /// When the cache was last written
+    flushed: Instant,
+    /// Number of frames written to the cache, but not published yet since a
+    /// reader holds the copy they are written to
+    unpublished: usize,
+}
+
+impl<C: Coords, V: Clone> Batch<C, V> {
+    /// Creates a new instance of an empty Batch
+    fn new() -> Self {
+        // The first frame is stored as soon as it comes
+        let now = Instant::now();
+        Batch {
+            frames: Vec::new(),
+            flushed: now.checked_sub(BATCH_DELAY).unwrap_or(now),
+            unpublished: 0,
+        }
+    }
+
+    /// Should the batch be stored now ?
+    fn is_due(&self) -> bool {
+        self.frames.len() >= BATCH_FRAMES || self.flushed.elapsed() >= BATCH_DELAY
+    }
+
+    /// Delay before the batch should be stored, or before publishing the
+    /// frames stored again. None if there is nothing to do
+    fn due_in(&self) -> Option<Duration> {
+        if self.frames.is_empty() && self.unpublished == 0 {
+            None
+        } else {
+            Some(BATCH_DELAY.saturating_sub(self.flushed.elapsed()))
+        }
+    }
+
+    /// Stores the frames of the batch in the cache, then grows the TimeIndex
+    /// once they are published
+    fn flush(&mut self, cache: &SharedDiffCache<C, V>, time_index: &Mutex<TimeIndex>) {
+        self.flushed = Instant::now();
+        if !self.frames.is_empty() {
+            // Writes all the frames to the cache at once.
+            // A delta reporting the same coordinates twice yields the same value twice,
+            // so overwriting is fine and keeps the populating thread alive
+            self.unpublished += self.frames.len();
+            cache.append(std::mem::take(&mut self.frames));
+        }
+        if self.unpublished > 0 && cache.publish() {
+            self.show(cache, time_index);
+        }
+    }
+
+    /// Grows the TimeIndex up to the frames published
+    fn show(&mut self, cache: &SharedDiffCache<C, V>, time_index: &Mutex<TimeIndex>) {
+        let decimated = cache.snapshot().decimated().collect();
+
+        // Locks the TimeIndex, adds the new max indexes and shows the
+        // frames decimated to keep to the memory budget of the cache
+        let mut time_index = time_index.lock().unwrap();
+        (0..self.unpublished).for_each(|_| time_index.add_max());
+        time_index.decimated = decimated;
+        self.unpublished = 0;
+    }
+}
+
 #[cfg(test)]
 pub mod tests {
     use super::*;
+    use crate::diff_cache::DiffCache;
 
     #[test]
     fn visualize_str_default_val() {
@@ -157,4 +418,127 @@ pub mod tests {
         let delta: Vec<(i32, i32)> = other_string.delta(&string);
         assert_eq!(delta.len(), 4);
     }
+
+    #[test]
+    fn visualize_populate_cache_with() {
+        let cache = SharedDiffCache::new(DiffCache::new(' '));
+        let time_index = Arc::new(Mutex::new(TimeIndex::new(0, 0, 0)));
+        let states = vec!["ab".to_string(), "ac".to_string()];
+
+        let mut frames = Vec::new();
+        populate_cache_with(
+            cache.clone(),
+            time_index.clone(),
+            states.into_iter(),
+            |index, state, changes| frames.push((index, state.clone(), changes.to_vec())),
+        );
+
+        assert_eq!(
+            frames,
+            vec![
+                (1, "ab".to_string(), vec![((0, 0), 'a'), ((1, 0), 'b')]),
+                (2, "ac".to_string(), vec![((1, 0), 'c')]),
+            ]
+        );
+        assert_eq!(time_index.lock().unwrap().max, 2);
+        assert_eq!(cache.snapshot().search((1, 0), 2), Some(&'c'));
+    }
+
+    #[test]
+    fn visualize_populate_cache_stall() {
+        let cache = SharedDiffCache::new(DiffCache::new(' '));
+        let time_index = Arc::new(Mutex::new(TimeIndex::new(0, 0, 0)));
+        let stored = time_index.clone();
+        // The frames computed before the states stall are stored in the meantime
+        let states = (0..4).map(|i| {
+            if i == 3 {
+                std::thread::sleep(BATCH_DELAY * 10);
+                assert_eq!(stored.lock().unwrap().max, 3);
+            }
+            i.to_string()
+        });
+
+        populate_cache(cache.clone(), time_index.clone(), states);
+        assert_eq!(time_index.lock().unwrap().max, 4);
+        assert_eq!(cache.snapshot().search((0, 0), 4), Some(&'3'));
+    }
+
+    #[test]
+    fn visualize_populate_cache_parallel() {
+        let states = || (0..2000).map(|i| format!("{:>5}\n{}", i, i % 7));
+        let populate = |workers: Option<usize>| {
+            let cache = SharedDiffCache::new(DiffCache::new(' '));
+            let time_index = Arc::new(Mutex::new(TimeIndex::new(0, 0, 0)));
+            let mut frames = Vec::new();
+            let on_frame = |index: usize, state: &String, changes: &[((i32, i32), char)]| {
+                frames.push((index, state.clone(), changes.to_vec()))
+            };
+            match workers {
+                Some(workers) => populate_cache_parallel(
+                    cache.clone(),
+                    time_index.clone(),
+                    states(),
+                    workers,
+                    on_frame,
+                ),
+                None => populate_cache_with(cache.clone(), time_index.clone(), states(), on_frame),
+            }
+            assert_eq!(time_index.lock().unwrap().max, 2000);
+            let cache = cache.snapshot();
+            let values: Vec<char> = (1..=2000)
+                .step_by(97)
+                .flat_map(|i| (0..10).map(move |p| ((p % 5, p / 5), i)))
+                .map(|(c, i)| *cache.search_or_default(c, i))
+                .collect();
+            (frames, values)
+        };
+
+        let sequential = populate(None);
+        assert_eq!(sequential.0.len(), 2000);
+        assert_eq!(populate(Some(1)), sequential);
+        assert_eq!(populate(Some(4)), sequential);
+    }
+
+    /// A state whose changes can not be computed for the 50th one
+    #[derive(Debug, Default)]
+    struct Faulty(usize);
+
+    impl Visualize<(i32, i32), char> for Faulty {
+        fn get(&self, _: &(i32, i32)) -> Option<char> {
+            Some('#')
+        }
+
+        fn delta(&self, _: &Self) -> Vec<(i32, i32)> {
+            assert_ne!(self.0, 50, "Faulty state");
+            vec![(self.0 as i32, 0)]
+        }
+
+        fn default_val(&self) -> char {
+            ' '
+        }
+    }
+
+    #[test]
+    fn visualize_populate_cache_parallel_panic() {
+        let cache = SharedDiffCache::new(DiffCache::new(' '));
+        let time_index = Arc::new(Mutex::new(TimeIndex::new(0, 0, 0)));
+        let (populated, result) = crossbeam_channel::bounded(1);
+        let (shared, stored) = (cache.clone(), time_index.clone());
+        std::thread::spawn(move || {
+            let states = (0..1000).map(Faulty);
+            let populate = AssertUnwindSafe(|| {
+                populate_cache_parallel(shared, stored, states, 2, |_, _, _| ())
+            });
+            let _ = populated.send(panic::catch_unwind(populate).is_err());
+        });
+
+        // The panic is raised again once the frames before it are stored,
+        // instead of waiting for the lost frame
+        let panicked = result.recv_timeout(Duration::from_secs(10));
+        assert_eq!(panicked, Ok(true));
+        assert_eq!(time_index.lock().unwrap().max, 50);
+        let cache = cache.snapshot();
+        assert_eq!(cache.search((49, 0), 50), Some(&'#'));
+        assert_eq!(cache.search((51, 0), 1000), None);
+    }
 }